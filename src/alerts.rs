@@ -0,0 +1,397 @@
+use ethers::types::{Address, TxHash, U256};
+use ethers::utils::format_units;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::TemplatesConfig;
+
+// Blacklist state of a watched address as far as the monitor knows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlacklistState {
+    Unknown,
+    Blacklisted,
+    Cleared,
+}
+
+impl BlacklistState {
+    pub fn from_status(blacklisted: bool) -> Self {
+        if blacklisted {
+            BlacklistState::Blacklisted
+        } else {
+            BlacklistState::Cleared
+        }
+    }
+}
+
+// What the monitor should report after an observation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alert {
+    // First status seen for an address, recorded without notifying
+    Initial(BlacklistState),
+    // The address moved between blacklisted and cleared
+    Transition { from: BlacklistState, to: BlacklistState },
+    // The address is still in the state it was last notified about
+    Reminder(BlacklistState),
+}
+
+#[derive(Debug)]
+struct Tracked {
+    state: BlacklistState,
+    last_notified: Option<Instant>,
+}
+
+/// Turns raw `isBlackListed` observations into one alert per state transition.
+///
+/// `Unknown -> Blacklisted/Cleared` only establishes the baseline; every later change
+/// between `Blacklisted` and `Cleared` yields exactly one `Transition`. When a
+/// re-notification interval is set and the current state was notified, a `Reminder`
+/// is produced each time that interval passes without another transition.
+pub struct AlertTracker {
+    tracked: HashMap<Address, Tracked>,
+    renotify_interval: Option<Duration>,
+}
+
+impl AlertTracker {
+    pub fn new(renotify_interval: Option<Duration>) -> Self {
+        AlertTracker {
+            tracked: HashMap::new(),
+            renotify_interval,
+        }
+    }
+
+    pub fn state(&self, address: Address) -> BlacklistState {
+        self.tracked
+            .get(&address)
+            .map(|tracked| tracked.state)
+            .unwrap_or(BlacklistState::Unknown)
+    }
+
+    /// Applies a new re-notification interval to every tracked address.
+    pub fn set_renotify_interval(&mut self, renotify_interval: Option<Duration>) {
+        self.renotify_interval = renotify_interval;
+    }
+
+    pub fn known_count(&self) -> usize {
+        self.tracked.len()
+    }
+
+    /// Sets the state of an address without producing an alert, e.g. when
+    /// restoring persisted state or when an event implies the previous state.
+    pub fn assume(&mut self, address: Address, state: BlacklistState) {
+        self.tracked.insert(address, Tracked { state, last_notified: None });
+    }
+
+    /// Starts the re-notification interval for the current state of `address`.
+    pub fn mark_notified(&mut self, address: Address) {
+        if let Some(tracked) = self.tracked.get_mut(&address) {
+            tracked.last_notified = Some(Instant::now());
+        }
+    }
+
+    /// Records an observed status and returns the alert it causes, if any.
+    pub fn observe(&mut self, address: Address, blacklisted: bool) -> Option<Alert> {
+        let to = BlacklistState::from_status(blacklisted);
+        let renotify_interval = self.renotify_interval;
+        let tracked = self.tracked.entry(address).or_insert(Tracked {
+            state: BlacklistState::Unknown,
+            last_notified: None,
+        });
+
+        let from = tracked.state;
+        if from == BlacklistState::Unknown {
+            tracked.state = to;
+            return Some(Alert::Initial(to));
+        }
+        if from != to {
+            tracked.state = to;
+            tracked.last_notified = None;
+            return Some(Alert::Transition { from, to });
+        }
+
+        match (renotify_interval, tracked.last_notified) {
+            (Some(interval), Some(last_notified)) if last_notified.elapsed() >= interval => {
+                tracked.last_notified = Some(Instant::now());
+                Some(Alert::Reminder(to))
+            }
+            _ => None,
+        }
+    }
+}
+
+// The kinds of Telegram alerts the monitor sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    Added,
+    Removed,
+    Destroyed,
+    Reminder,
+    // Freeze or unfreeze calls seen in the mempool, before they are mined
+    PendingAdded,
+    PendingRemoved,
+    // An earlier alert whose block left the canonical chain
+    ReorgedOut,
+}
+
+impl AlertKind {
+    // Key used to remember which alerts were already sent
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::Added => "blacklisted",
+            AlertKind::Removed => "unblacklisted",
+            AlertKind::Destroyed => "destroyed",
+            AlertKind::Reminder => "reminder",
+            AlertKind::PendingAdded => "pending_blacklisted",
+            AlertKind::PendingRemoved => "pending_unblacklisted",
+            AlertKind::ReorgedOut => "reorged_out",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            AlertKind::Added | AlertKind::Destroyed | AlertKind::PendingAdded => Severity::Critical,
+            AlertKind::Removed | AlertKind::PendingRemoved | AlertKind::ReorgedOut => Severity::Warning,
+            AlertKind::Reminder => Severity::Info,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    fn emoji(&self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ️",
+            Severity::Warning => "⚠️",
+            Severity::Critical => "🚨",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Info => "INFO",
+            Severity::Warning => "WARNING",
+            Severity::Critical => "CRITICAL",
+        }
+    }
+}
+
+// Values substituted into an alert template
+pub struct AlertContext<'a> {
+    pub chain: &'a str,
+    pub token: &'a str,
+    pub decimals: u32,
+    pub label: &'a str,
+    pub address: Address,
+    pub block: u64,
+    pub amount: Option<U256>,
+    pub state: BlacklistState,
+    pub tx: Option<TxHash>,
+}
+
+// Values substituted into a monitor health template
+pub struct HealthContext<'a> {
+    pub chain: &'a str,
+    pub token: &'a str,
+    pub failures: u32,
+    pub error: &'a str,
+}
+
+/// Message templates for each alert kind.
+///
+/// Templates may use `{emoji}`, `{severity}`, `{chain}`, `{token}`, `{label}`, `{address}`,
+/// `{block}`, `{amount}` (destroyed funds only), `{state}` and `{tx}` (pending calls only). The
+/// pending templates use the current head as `{block}`, the reorged out template the block the
+/// earlier alert came from. The monitor health templates
+/// may use `{emoji}`, `{severity}`, `{chain}`, `{token}`, `{failures}` and `{error}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertTemplates {
+    added: String,
+    removed: String,
+    destroyed: String,
+    reminder: String,
+    pending_added: String,
+    pending_removed: String,
+    reorged_out: String,
+    degraded: String,
+    recovered: String,
+}
+
+impl AlertTemplates {
+    /// Applies the configured templates over the defaults.
+    pub fn from_config(config: &TemplatesConfig) -> Self {
+        let template = |value: &Option<String>, default: &str| value.clone().unwrap_or_else(|| default.to_string());
+        AlertTemplates {
+            added: template(
+                &config.added,
+                "{emoji} [{severity}] Address {label} ({address}) has been BLACKLISTED by {token} contract on {chain} at block {block}!",
+            ),
+            removed: template(
+                &config.removed,
+                "{emoji} [{severity}] Address {label} ({address}) has been unblacklisted by {token} contract on {chain} at block {block}!",
+            ),
+            destroyed: template(
+                &config.destroyed,
+                "{emoji} [{severity}] Blacklisted funds of {label} ({address}) were destroyed on {chain} at block {block}: {amount} {token}",
+            ),
+            reminder: template(
+                &config.reminder,
+                "{emoji} [{severity}] Reminder: address {label} ({address}) is still {state} by {token} on {chain}.",
+            ),
+            pending_added: template(
+                &config.pending_added,
+                "{emoji} [{severity}] Pending transaction {tx} would BLACKLIST {label} ({address}) in the {token} contract on {chain} (seen at block {block})",
+            ),
+            pending_removed: template(
+                &config.pending_removed,
+                "{emoji} [{severity}] Pending transaction {tx} would unblacklist {label} ({address}) in the {token} contract on {chain} (seen at block {block})",
+            ),
+            reorged_out: template(
+                &config.reorged_out,
+                "{emoji} [{severity}] Block {block} on {chain} was reorged out: the {token} alert it caused for {label} ({address}) no longer holds, the address is {state}.",
+            ),
+            degraded: template(
+                &config.degraded,
+                "{emoji} [{severity}] The {token} monitor on {chain} is degraded after {failures} consecutive failure(s): {error}",
+            ),
+            recovered: template(
+                &config.recovered,
+                "{emoji} [{severity}] The {token} monitor on {chain} recovered after {failures} consecutive failure(s).",
+            ),
+        }
+    }
+
+    /// Renders the alert sent when a monitor becomes degraded, or recovers when `degraded` is false.
+    pub fn render_health(&self, degraded: bool, context: &HealthContext) -> String {
+        let (template, severity) = match degraded {
+            true => (&self.degraded, Severity::Warning),
+            false => (&self.recovered, Severity::Info),
+        };
+        template
+            .replace("{emoji}", severity.emoji())
+            .replace("{severity}", severity.name())
+            .replace("{chain}", context.chain)
+            .replace("{token}", context.token)
+            .replace("{failures}", &context.failures.to_string())
+            .replace("{error}", context.error)
+    }
+
+    pub fn render(&self, kind: AlertKind, context: &AlertContext) -> String {
+        let template = match kind {
+            AlertKind::Added => &self.added,
+            AlertKind::Removed => &self.removed,
+            AlertKind::Destroyed => &self.destroyed,
+            AlertKind::Reminder => &self.reminder,
+            AlertKind::PendingAdded => &self.pending_added,
+            AlertKind::PendingRemoved => &self.pending_removed,
+            AlertKind::ReorgedOut => &self.reorged_out,
+        };
+        let severity = kind.severity();
+        let amount = context
+            .amount
+            .and_then(|amount| format_units(amount, context.decimals).ok())
+            .unwrap_or_default();
+        let state = match context.state {
+            BlacklistState::Unknown => "unknown",
+            BlacklistState::Blacklisted => "blacklisted",
+            BlacklistState::Cleared => "not blacklisted",
+        };
+        template
+            .replace("{emoji}", severity.emoji())
+            .replace("{severity}", severity.name())
+            .replace("{chain}", context.chain)
+            .replace("{token}", context.token)
+            .replace("{label}", context.label)
+            .replace("{address}", &format!("{:?}", context.address))
+            .replace("{block}", &context.block.to_string())
+            .replace("{amount}", &amount)
+            .replace("{state}", state)
+            .replace("{tx}", &context.tx.map(|tx| format!("{:?}", tx)).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: Address = Address::repeat_byte(0xaa);
+
+    #[test]
+    fn first_observation_only_sets_the_baseline() {
+        let mut tracker = AlertTracker::new(None);
+        assert_eq!(tracker.observe(ADDRESS, true), Some(Alert::Initial(BlacklistState::Blacklisted)));
+        assert_eq!(tracker.state(ADDRESS), BlacklistState::Blacklisted);
+        assert_eq!(tracker.observe(ADDRESS, true), None);
+
+        let other = Address::repeat_byte(0xbb);
+        assert_eq!(tracker.observe(other, false), Some(Alert::Initial(BlacklistState::Cleared)));
+        assert_eq!(tracker.known_count(), 2);
+    }
+
+    #[test]
+    fn every_change_is_one_transition() {
+        let mut tracker = AlertTracker::new(None);
+        tracker.observe(ADDRESS, false);
+        assert_eq!(
+            tracker.observe(ADDRESS, true),
+            Some(Alert::Transition { from: BlacklistState::Cleared, to: BlacklistState::Blacklisted })
+        );
+        assert_eq!(tracker.observe(ADDRESS, true), None);
+        assert_eq!(
+            tracker.observe(ADDRESS, false),
+            Some(Alert::Transition { from: BlacklistState::Blacklisted, to: BlacklistState::Cleared })
+        );
+        assert_eq!(tracker.observe(ADDRESS, false), None);
+    }
+
+    #[test]
+    fn reminders_need_an_interval_and_a_notified_state() {
+        let mut tracker = AlertTracker::new(Some(Duration::ZERO));
+        tracker.observe(ADDRESS, true);
+        assert_eq!(tracker.observe(ADDRESS, true), None);
+
+        tracker.mark_notified(ADDRESS);
+        assert_eq!(tracker.observe(ADDRESS, true), Some(Alert::Reminder(BlacklistState::Blacklisted)));
+        assert_eq!(tracker.observe(ADDRESS, true), Some(Alert::Reminder(BlacklistState::Blacklisted)));
+
+        tracker.set_renotify_interval(None);
+        assert_eq!(tracker.observe(ADDRESS, true), None);
+    }
+
+    #[test]
+    fn reminders_wait_for_the_interval() {
+        let mut tracker = AlertTracker::new(Some(Duration::from_secs(3600)));
+        tracker.observe(ADDRESS, true);
+        tracker.mark_notified(ADDRESS);
+        assert_eq!(tracker.observe(ADDRESS, true), None);
+    }
+
+    #[test]
+    fn a_transition_restarts_the_reminders() {
+        let mut tracker = AlertTracker::new(Some(Duration::ZERO));
+        tracker.observe(ADDRESS, true);
+        tracker.mark_notified(ADDRESS);
+        assert!(matches!(tracker.observe(ADDRESS, false), Some(Alert::Transition { .. })));
+        assert_eq!(tracker.observe(ADDRESS, false), None);
+    }
+
+    #[test]
+    fn assume_sets_the_state_without_an_alert() {
+        let mut tracker = AlertTracker::new(Some(Duration::ZERO));
+        tracker.assume(ADDRESS, BlacklistState::Blacklisted);
+        assert_eq!(tracker.state(ADDRESS), BlacklistState::Blacklisted);
+        assert_eq!(tracker.observe(ADDRESS, true), None);
+        assert_eq!(
+            tracker.observe(ADDRESS, false),
+            Some(Alert::Transition { from: BlacklistState::Blacklisted, to: BlacklistState::Cleared })
+        );
+
+        // An assumed state was never notified, so it is not reminded
+        tracker.assume(ADDRESS, BlacklistState::Cleared);
+        assert_eq!(tracker.observe(ADDRESS, false), None);
+    }
+}
@@ -0,0 +1,228 @@
+use anyhow::Context;
+use ethers::prelude::*;
+use std::sync::Arc;
+
+use crate::blacklist_events::BlacklistChange;
+use crate::freeze::FreezeChecker;
+use crate::retry::RetryPolicy;
+use crate::store::Store;
+
+// Chunk size bounds for eth_getLogs ranges
+const INITIAL_CHUNK_SIZE: u64 = 10_000;
+const MAX_CHUNK_SIZE: u64 = 500_000;
+
+/// Scans every freeze, unfreeze and destroy log of the checker's token from the last
+/// synced block (or the token deployment block) up to the current head and stores
+/// them in `store`.
+///
+/// The chunk size halves whenever the provider rejects a range for returning too
+/// many results and grows again after successful requests. Other failures, such as rate
+/// limits, are retried with `retry` on the same range.
+pub async fn run<M: Middleware<Error = ProviderError> + 'static>(
+    client: Arc<M>,
+    checker: &dyn FreezeChecker,
+    store: &Store,
+    retry: RetryPolicy,
+) -> anyhow::Result<()> {
+    let head = client
+        .get_block_number()
+        .await
+        .context("Failed to fetch block number")?
+        .as_u64();
+    let chain = checker.chain();
+    let token = checker.token();
+    let mut from = match store.synced_block(chain.chain_id, token)? {
+        Some(synced) => synced + 1,
+        None => checker.deployment_block(),
+    };
+    println_time!("Backfilling {} freeze events on {} for blocks {}..={}", checker.name(), chain.name, from, head);
+
+    let mut chunk_size = INITIAL_CHUNK_SIZE;
+    let mut stored = 0usize;
+    while from <= head {
+        let to = head.min(from + chunk_size - 1);
+        let filter = checker.event_filter().from_block(from).to_block(to);
+        // `None` when the range is too large, which a retry of the same range cannot fix
+        let logs = retry
+            .run("Fetching freeze logs", || async {
+                match client.get_logs(&filter).await {
+                    Ok(logs) => Ok(Some(logs)),
+                    Err(e) if chunk_size > 1 && is_too_many_results(&e.to_string()) => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            })
+            .await
+            .with_context(|| format!("Failed to fetch {} freeze logs for blocks {}..={}", checker.name(), from, to))?;
+        let Some(logs) = logs else {
+            chunk_size /= 2;
+            println_time!("Range {}..={} too large, retrying with {} blocks", from, to, chunk_size);
+            continue;
+        };
+
+        for event in logs.iter().filter_map(|log| checker.decode_log(log)) {
+            if store.record_event(&event)? {
+                stored += 1;
+            }
+        }
+        store.set_synced_block(chain.chain_id, token, to)?;
+        println_time!("Scanned blocks {}..={} ({} logs)", from, to, logs.len());
+
+        from = to + 1;
+        chunk_size = (chunk_size * 2).min(MAX_CHUNK_SIZE);
+    }
+
+    println_time!("{} backfill on {} complete, {} new event(s) stored", checker.name(), chain.name, stored);
+    Ok(())
+}
+
+// Providers word this differently, e.g. "query returned more than 10000 results",
+// "Log response size exceeded" or "block range is too wide". Rate limits do not match,
+// so they are retried instead of shrinking the range.
+fn is_too_many_results(message: &str) -> bool {
+    let message = message.to_lowercase();
+    ["query returned more than", "too many results", "block range", "response size exceeded"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Prints the stored freeze timeline of `address` across all chains and tokens.
+pub fn print_timeline(store: &Store, checkers: &[Arc<dyn FreezeChecker>], address: Address) -> anyhow::Result<()> {
+    let events = store.timeline(address)?;
+    if events.is_empty() {
+        println!("No freeze events stored for {:?}", address);
+        return Ok(());
+    }
+    for event in events {
+        let checker = checkers
+            .iter()
+            .find(|checker| checker.chain().chain_id == event.chain_id && checker.token() == event.token);
+        let token = checker
+            .map(|checker| checker.name().to_string())
+            .unwrap_or_else(|| format!("{:?}", event.token));
+        let chain = checker
+            .map(|checker| checker.chain().name.clone())
+            .unwrap_or_else(|| format!("chain {}", event.chain_id));
+        let description = match event.change {
+            BlacklistChange::Added => "added to blacklist".to_string(),
+            BlacklistChange::Removed => "removed from blacklist".to_string(),
+            BlacklistChange::Destroyed(amount) => format!("black funds destroyed ({} units)", amount),
+        };
+        println!(
+            "{:<16}  block {:>10}  {:<6}  {:<45}  tx {:?}",
+            chain, event.block_number, token, description, event.tx_hash
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::{JsonRpcError, MockError, MockProvider, MockResponse};
+    use std::time::Duration;
+
+    use crate::chains;
+    use crate::freeze::{CheckerSpec, FreezeList, SignatureChecker};
+
+    const TOKEN: Address = Address::repeat_byte(0x70);
+
+    fn checker() -> SignatureChecker {
+        let chain = Arc::new(chains::builtin(42161).unwrap());
+        SignatureChecker::new(CheckerSpec::builtin("USDT", FreezeList::TetherV2, TOKEN), chain).unwrap()
+    }
+
+    fn retry() -> RetryPolicy {
+        RetryPolicy { attempts: 3, base_delay: Duration::ZERO, max_delay: Duration::ZERO }
+    }
+
+    fn error(message: &str) -> MockResponse {
+        MockResponse::Error(JsonRpcError { code: -32005, message: message.to_string(), data: None })
+    }
+
+    // Answers eth_blockNumber with `head`, then `logs` in order
+    fn provider(head: u64, logs: Vec<MockResponse>) -> (Arc<Provider<MockProvider>>, MockProvider) {
+        let (provider, mock) = Provider::mocked();
+        // Responses are popped from the back
+        for response in logs.into_iter().rev() {
+            mock.push_response(response);
+        }
+        mock.push(U64::from(head)).unwrap();
+        (Arc::new(provider), mock)
+    }
+
+    fn no_logs() -> MockResponse {
+        MockResponse::Value(serde_json::json!([]))
+    }
+
+    fn assert_ranges(mock: &MockProvider, checker: &SignatureChecker, ranges: &[(u64, u64)]) {
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        for &(from, to) in ranges {
+            let filter = checker.event_filter().from_block(from).to_block(to);
+            mock.assert_request("eth_getLogs", [filter]).unwrap();
+        }
+        assert!(matches!(mock.assert_request("eth_getLogs", ()), Err(MockError::EmptyRequests)));
+    }
+
+    #[test]
+    fn recognises_range_and_result_count_errors() {
+        assert!(is_too_many_results("query returned more than 10000 results"));
+        assert!(is_too_many_results("Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range"));
+        assert!(is_too_many_results("block range is too wide"));
+        assert!(is_too_many_results("Too many results, please narrow the query"));
+        assert!(!is_too_many_results("rate limit exceeded"));
+        assert!(!is_too_many_results("daily request count limit exceeded"));
+        assert!(!is_too_many_results("header not found"));
+    }
+
+    #[tokio::test]
+    async fn halves_the_range_on_too_many_results_and_grows_up_to_the_maximum() {
+        let checker = checker();
+        let head = 1_999_999;
+        let mut logs = vec![error("query returned more than 10000 results")];
+        logs.extend((0..10).map(|_| no_logs()));
+        let (provider, mock) = provider(head, logs);
+        let store = Store::in_memory();
+        run(provider, &checker, &store, retry()).await.unwrap();
+
+        assert_ranges(
+            &mock,
+            &checker,
+            &[
+                (0, 9_999),
+                (0, 4_999),
+                (5_000, 14_999),
+                (15_000, 34_999),
+                (35_000, 74_999),
+                (75_000, 154_999),
+                (155_000, 314_999),
+                (315_000, 634_999),
+                (635_000, 1_134_999),
+                (1_135_000, 1_634_999),
+                (1_635_000, 1_999_999),
+            ],
+        );
+        assert_eq!(store.synced_block(42161, TOKEN).unwrap(), Some(head));
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limits_on_the_same_range() {
+        let checker = checker();
+        let (provider, mock) = provider(5_000, vec![error("rate limit exceeded"), no_logs()]);
+        let store = Store::in_memory();
+        run(provider, &checker, &store, retry()).await.unwrap();
+
+        assert_ranges(&mock, &checker, &[(0, 5_000), (0, 5_000)]);
+        assert_eq!(store.synced_block(42161, TOKEN).unwrap(), Some(5_000));
+    }
+
+    #[tokio::test]
+    async fn resumes_after_the_synced_block() {
+        let checker = checker();
+        let (provider, mock) = provider(30_000, vec![no_logs()]);
+        let store = Store::in_memory();
+        store.set_synced_block(42161, TOKEN, 24_999).unwrap();
+        run(provider, &checker, &store, retry()).await.unwrap();
+
+        assert_ranges(&mock, &checker, &[(25_000, 30_000)]);
+    }
+}
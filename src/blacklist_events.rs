@@ -0,0 +1,57 @@
+use anyhow::Context;
+use ethers::prelude::*;
+use std::sync::Arc;
+
+use crate::freeze::FreezeChecker;
+
+// Maximum number of blocks requested in a single eth_getLogs call
+const MAX_LOG_RANGE: u64 = 2_000;
+
+// What happened to a blacklisted address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlacklistChange {
+    Added,
+    Removed,
+    Destroyed(U256),
+}
+
+// A decoded freeze list log of a token
+#[derive(Debug, Clone)]
+pub struct BlacklistEvent {
+    pub chain_id: u64,
+    pub token: Address,
+    pub address: Address,
+    pub change: BlacklistChange,
+    pub block_number: u64,
+    // Unknown for events read back from the database
+    pub block_hash: Option<H256>,
+    pub tx_hash: H256,
+    pub log_index: U256,
+}
+
+/// Fetches the freeze list events of every address between `from` and `to` (inclusive),
+/// split into `MAX_LOG_RANGE` sized `eth_getLogs` requests and ordered as they were mined.
+///
+/// Some issuers (USDT) do not index the address parameter, so logs can only be narrowed
+/// by contract and event signature; watched addresses are matched after decoding.
+pub async fn fetch_events<M: Middleware + 'static>(
+    client: Arc<M>,
+    checker: &dyn FreezeChecker,
+    from: u64,
+    to: u64,
+) -> anyhow::Result<Vec<BlacklistEvent>> {
+    let mut events = Vec::new();
+    let mut start = from;
+    while start <= to {
+        let end = to.min(start + MAX_LOG_RANGE - 1);
+        let filter = checker.event_filter().from_block(start).to_block(end);
+        let logs = client
+            .get_logs(&filter)
+            .await
+            .with_context(|| format!("Failed to fetch {} freeze logs for blocks {}..={}", checker.name(), start, end))?;
+        events.extend(logs.iter().filter_map(|log| checker.decode_log(log)));
+        start = end + 1;
+    }
+    events.sort_by_key(|event| (event.block_number, event.log_index));
+    Ok(events)
+}
@@ -0,0 +1,147 @@
+use ethers::types::Address;
+use std::collections::HashMap;
+
+use crate::freeze::FreezeList;
+
+// Everything the crate needs to know about one EVM network
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainInfo {
+    pub chain_id: u64,
+    pub name: String,
+    pub rpc_urls: Vec<String>,
+    // Number of RPC endpoints that must agree on a blacklist read, 1 disables the quorum
+    pub quorum: usize,
+    pub ws_url: Option<String>,
+    // Private relays that take sweeps and rescues out of the public mempool
+    pub relay_urls: Vec<String>,
+    pub native_symbol: String,
+    // Known token contracts by symbol, e.g. "USDT"
+    pub tokens: HashMap<String, Address>,
+    // Blocks to wait before treating a transaction or observation as final
+    pub confirmations: u64,
+    // OP Stack rollups charge an L1 data fee on top of the gas
+    pub op_stack: bool,
+}
+
+impl ChainInfo {
+    pub fn token(&self, symbol: &str) -> Option<Address> {
+        self.tokens.get(&symbol.to_uppercase()).copied()
+    }
+}
+
+// A network known without any configuration
+struct BuiltinChain {
+    chain_id: u64,
+    name: &'static str,
+    native_symbol: &'static str,
+    confirmations: u64,
+    op_stack: bool,
+    // Symbol, address and the freeze list the token implements, if any
+    tokens: &'static [(&'static str, &'static str, Option<FreezeList>)],
+}
+
+const BUILTIN_CHAINS: &[BuiltinChain] = &[
+    BuiltinChain {
+        chain_id: 1,
+        name: "Ethereum",
+        native_symbol: "ETH",
+        confirmations: 3,
+        op_stack: false,
+        tokens: &[
+            ("USDT", "0xdAC17F958D2ee523a2206206994597C13D831ec7", Some(FreezeList::Tether)),
+            ("USDC", "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", Some(FreezeList::FiatToken)),
+        ],
+    },
+    BuiltinChain {
+        chain_id: 42161,
+        name: "Arbitrum One",
+        native_symbol: "ETH",
+        confirmations: 10,
+        op_stack: false,
+        tokens: &[
+            ("USDT", "0xFd086bC7CD5C481DCC9C85ebE478A1C0b69FCbb9", Some(FreezeList::TetherV2)),
+            ("USDC", "0xaf88d065e77c8cC2239327C5EDb3A432268e5831", Some(FreezeList::FiatToken)),
+        ],
+    },
+    BuiltinChain {
+        chain_id: 10,
+        name: "Optimism",
+        native_symbol: "ETH",
+        confirmations: 10,
+        op_stack: true,
+        tokens: &[
+            // Bridged from Ethereum through the standard bridge, which has no freeze list
+            ("USDT", "0x94b008aA00579c1307B0EF2c499aD98a8ce58e58", None),
+            ("USDC", "0x0b2C639c533813f4Aa9D7837CAf62653d097Ff85", Some(FreezeList::FiatToken)),
+        ],
+    },
+    BuiltinChain {
+        chain_id: 137,
+        name: "Polygon",
+        native_symbol: "POL",
+        confirmations: 64,
+        op_stack: false,
+        tokens: &[
+            ("USDT", "0xc2132D05D31c914a87C6611C10748AEb04B58e8F", Some(FreezeList::TetherV2)),
+            ("USDC", "0x3c499c542cEF5E3811e1192ce70d8cC03d5c3359", Some(FreezeList::FiatToken)),
+        ],
+    },
+    // The BSC tokens are Binance-Peg wrappers without an issuer freeze list
+    BuiltinChain {
+        chain_id: 56,
+        name: "BNB Smart Chain",
+        native_symbol: "BNB",
+        confirmations: 15,
+        op_stack: false,
+        tokens: &[
+            ("USDT", "0x55d398326f99059fF775485246999027B3197955", None),
+            ("USDC", "0x8AC76a51cc950d9822D68b83fE1Ad97B32Cd580d", None),
+        ],
+    },
+];
+
+/// Returns the registry entry for a built-in chain, without RPC endpoints.
+pub fn builtin(chain_id: u64) -> Option<ChainInfo> {
+    BUILTIN_CHAINS
+        .iter()
+        .find(|builtin| builtin.chain_id == chain_id)
+        .map(|builtin| ChainInfo {
+            chain_id: builtin.chain_id,
+            name: builtin.name.to_string(),
+            rpc_urls: Vec::new(),
+            quorum: 1,
+            ws_url: None,
+            relay_urls: Vec::new(),
+            native_symbol: builtin.native_symbol.to_string(),
+            tokens: builtin
+                .tokens
+                .iter()
+                .map(|(symbol, address, _)| (symbol.to_string(), address.parse().expect("valid address")))
+                .collect(),
+            confirmations: builtin.confirmations,
+            op_stack: builtin.op_stack,
+        })
+}
+
+/// Freeze list of a built-in token of a built-in chain: `None` when the token is not
+/// built in, `Some(None)` when it has no freeze list.
+pub fn builtin_freeze_list(chain_id: u64, symbol: &str) -> Option<Option<FreezeList>> {
+    BUILTIN_CHAINS
+        .iter()
+        .find(|builtin| builtin.chain_id == chain_id)?
+        .tokens
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(symbol))
+        .map(|(_, _, list)| *list)
+}
+
+/// Built-in tokens of a built-in chain that have a freeze list.
+pub fn builtin_freeze_tokens(chain_id: u64) -> Vec<&'static str> {
+    BUILTIN_CHAINS
+        .iter()
+        .filter(|builtin| builtin.chain_id == chain_id)
+        .flat_map(|builtin| builtin.tokens.iter())
+        .filter(|(_, _, list)| list.is_some())
+        .map(|(symbol, _, _)| *symbol)
+        .collect()
+}
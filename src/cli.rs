@@ -0,0 +1,158 @@
+use clap::{Parser, Subcommand};
+use ethers::prelude::*;
+use ethers::utils::parse_ether;
+
+/// Watches stablecoin freeze lists and moves funds with local keys.
+///
+/// Every command reads the same configuration: the file given by --config, CONFIG_FILE
+/// or config.toml, with environment overrides. Private keys only come from the environment.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Configuration file, instead of CONFIG_FILE or config.toml
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<String>,
+
+    /// Runs the monitor when no command is given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Watch the freeze lists of every configured chain and alert on watched addresses
+    Monitor {
+        /// Check and simulate rescues without sending them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Store the full freeze history of every configured token in the database
+    Backfill,
+    /// Print the stored freeze history of an address
+    Timeline { address: Address },
+    /// Print the current freeze status of an address on every configured chain and token
+    #[command(alias = "check")]
+    CheckBlacklist {
+        address: Address,
+        /// Only this chain id
+        #[arg(long)]
+        chain: Option<u64>,
+        /// Only this freeze list, e.g. USDT
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Print how much of an owner's tokens a spender may move with transferFrom
+    Allowance {
+        /// Token symbol or address
+        token: String,
+        owner: Address,
+        spender: Address,
+        #[command(flatten)]
+        chain: ChainArgs,
+    },
+    /// Send the whole token and native balances of PRIVATE_KEY to the sweep recipient
+    Sweep {
+        #[command(flatten)]
+        sweep: SweepArgs,
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+    /// Keep sweeping the balances of PRIVATE_KEY until Ctrl-C or SIGTERM
+    Sweeper {
+        #[command(flatten)]
+        sweep: SweepArgs,
+        /// Seconds between balance checks, instead of sweep.interval_secs
+        #[arg(long, value_name = "SECS")]
+        interval: Option<u64>,
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+    /// Transfer tokens signed with PRIVATE_KEY
+    Transfer {
+        /// Token symbol or address
+        token: String,
+        to: Address,
+        /// Amount in the token's smallest unit
+        #[arg(value_parser = parse_units)]
+        amount: U256,
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+    /// Approve a spender, signed with PRIVATE_KEY_OWNER (or PRIVATE_KEY)
+    Approve {
+        /// Token symbol or address
+        token: String,
+        spender: Address,
+        /// Amount in the token's smallest unit
+        #[arg(value_parser = parse_units)]
+        amount: U256,
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+    /// Move approved tokens with transferFrom, signed with PRIVATE_KEY_SPENDER
+    TransferFrom {
+        /// Token symbol or address
+        token: String,
+        from: Address,
+        to: Address,
+        /// Amount in the token's smallest unit
+        #[arg(value_parser = parse_units)]
+        amount: U256,
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+    /// Configuration commands
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Validate the configuration and compare the chain id of every RPC endpoint
+    Check,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ChainArgs {
+    /// Chain id, instead of chain_id (CHAIN_ID) or the first configured chain
+    #[arg(long)]
+    pub chain: Option<u64>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SweepArgs {
+    /// Instead of sweep.recipient (RECIPIENT_ADDRESS)
+    #[arg(long)]
+    pub recipient: Option<Address>,
+    /// Native balance below which nothing is sent, instead of sweep.min_balance
+    #[arg(long, value_name = "ETHER", value_parser = parse_ether_amount)]
+    pub min_balance: Option<U256>,
+    /// Token symbol or address to sweep before the native balance, instead of sweep.tokens;
+    /// may be repeated
+    #[arg(long = "token", value_name = "TOKEN")]
+    pub tokens: Vec<String>,
+    /// Native balance left in the account, instead of sweep.reserve
+    #[arg(long, value_name = "ETHER", value_parser = parse_ether_amount)]
+    pub reserve: Option<U256>,
+}
+
+// Flags shared by the commands that send a transaction
+#[derive(Debug, clap::Args)]
+pub struct TxArgs {
+    #[command(flatten)]
+    pub chain: ChainArgs,
+    /// Check balances and simulate the transaction without sending it
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+// Decimal amounts; `U256::from_str` would read them as hex
+fn parse_units(value: &str) -> Result<U256, String> {
+    U256::from_dec_str(value).map_err(|e| e.to_string())
+}
+
+fn parse_ether_amount(value: &str) -> Result<U256, String> {
+    parse_ether(value).map_err(|e| e.to_string())
+}
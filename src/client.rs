@@ -0,0 +1,112 @@
+use ethers::prelude::*;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::chains::ChainInfo;
+use crate::contracts::Erc20;
+use crate::error::{Error, Result};
+use crate::relay::{Relay, RelaySettings};
+use crate::rpc::ProviderPool;
+
+// Provider every feature talks to a chain through
+pub type ChainProvider = Provider<ProviderPool>;
+// Chain provider that signs transactions with a local key
+pub type SignerClient = SignerMiddleware<Arc<ChainProvider>, LocalWallet>;
+
+// RPC settings shared by the providers of every chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RpcSettings {
+    // Per-request timeout before failing over to the next endpoint
+    pub timeout: Duration,
+    // How often endpoints are probed
+    pub health_check_interval: Duration,
+}
+
+/// Connection to one chain: the pooled provider plus the signers and contract
+/// bindings built on top of it, so every feature shares endpoints and settings.
+#[derive(Clone)]
+pub struct ChainClient {
+    pub chain: Arc<ChainInfo>,
+    provider: Arc<ChainProvider>,
+    // When the chain has private relays
+    relay: Option<Relay>,
+}
+
+impl ChainClient {
+    /// Builds the provider pool of `chain` and starts its background health checks.
+    pub fn connect(chain: Arc<ChainInfo>, settings: RpcSettings, relay: RelaySettings) -> Result<Self> {
+        let pool = ProviderPool::new(&chain, settings.timeout).map_err(|e| Error::Config(format!("{:#}", e)))?;
+        pool.spawn_health_checks(settings.health_check_interval);
+        let relay = (!chain.relay_urls.is_empty())
+            .then(|| Relay::new(chain.relay_urls.clone(), relay, settings.timeout))
+            .transpose()?;
+        Ok(ChainClient {
+            chain,
+            provider: Arc::new(Provider::new(pool)),
+            relay,
+        })
+    }
+
+    pub fn provider(&self) -> Arc<ChainProvider> {
+        self.provider.clone()
+    }
+
+    /// Private relays that sweeps and rescues are submitted through, if the chain has any.
+    pub fn relay(&self) -> Option<&Relay> {
+        self.relay.as_ref()
+    }
+
+    /// Signing client for `private_key`, bound to this chain's id.
+    pub fn signer(&self, private_key: &str) -> Result<Arc<SignerClient>> {
+        let wallet = parse_wallet(private_key, "private key")?;
+        Ok(Arc::new(SignerMiddleware::new(
+            self.provider(),
+            wallet.with_chain_id(self.chain.chain_id),
+        )))
+    }
+
+    /// Signing client for the first of `keys` that is set in the environment.
+    pub fn signer_from_env(&self, keys: &[&str]) -> Result<Arc<SignerClient>> {
+        let private_key = keys
+            .iter()
+            .find_map(|key| env::var(key).ok().filter(|value| !value.trim().is_empty()))
+            .ok_or_else(|| Error::Config(format!("missing {}", keys.join(" or "))))?;
+        self.signer(&private_key)
+    }
+
+    /// Signing clients for the comma separated keys in the environment variable `key`,
+    /// none when it is unset.
+    pub fn signers_from_env(&self, key: &str) -> Result<Vec<Arc<SignerClient>>> {
+        env::var(key)
+            .unwrap_or_default()
+            .split(',')
+            .filter(|private_key| !private_key.trim().is_empty())
+            .map(|private_key| self.signer(private_key))
+            .collect()
+    }
+
+    /// Resolves a token symbol known on this chain (e.g. `USDT`) or a literal address.
+    pub fn token(&self, token: &str) -> Result<Address> {
+        match self.chain.token(token) {
+            Some(address) => Ok(address),
+            None => token
+                .parse()
+                .map_err(|_| Error::Config(format!("unknown token {} on {}", token, self.chain.name))),
+        }
+    }
+
+    /// Read-only binding of an ERC-20 token on this chain.
+    pub fn erc20(&self, token: Address) -> Erc20<ChainProvider> {
+        Erc20::new(token, self.provider())
+    }
+}
+
+/// Parses a hex private key, failing with `invalid <what>`. The parse error is not kept,
+/// so the key can never be echoed back.
+pub fn parse_wallet(private_key: &str, what: &str) -> Result<LocalWallet> {
+    private_key
+        .trim()
+        .parse()
+        .map_err(|_| Error::Config(format!("invalid {}", what)))
+}
@@ -0,0 +1,1174 @@
+use anyhow::Context;
+use ethers::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::alerts::AlertTemplates;
+use crate::chains::{self, ChainInfo};
+use crate::client::{self, ChainClient, RpcSettings};
+use crate::relay::{RelayMethod, RelaySettings, RELAY_AUTH_KEY_VAR};
+use crate::error::Error;
+use crate::freeze::{CheckerSpec, FreezeList, SignatureChecker};
+use crate::retry::RetryPolicy;
+use crate::rpc;
+use crate::watchlist::{self, AlertDirections, WatchEntry};
+
+// Read when neither --config nor CONFIG_FILE is given; unlike those it may be missing
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+// Private keys are only ever read from the environment
+const PRIVATE_KEY_VARS: &[&str] = &["PRIVATE_KEY", "PRIVATE_KEY_OWNER", "PRIVATE_KEY_SPENDER", RELAY_AUTH_KEY_VAR];
+/// Comma separated keys of the watched addresses the monitor rescues funds from.
+pub const RESCUE_KEYS_VAR: &str = "RESCUE_PRIVATE_KEYS";
+
+// Layout of the configuration file; every value is optional and can be overridden by
+// the environment variable named in `apply_env`
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    database_path: Option<String>,
+    // Chain used by the transaction commands
+    chain_id: Option<u64>,
+    // Keyed by chain id
+    chains: BTreeMap<String, ChainSection>,
+    // Freeze lists watched on chains without their own `issuers`
+    issuers: Option<Vec<String>>,
+    // Freeze list definitions and overrides, keyed by issuer name
+    issuer: BTreeMap<String, IssuerSection>,
+    monitor: MonitorSection,
+    rpc: RpcSection,
+    retry: RetrySection,
+    watchlist: WatchlistSection,
+    sweep: SweepSection,
+    rescue: RescueSection,
+    relay: RelaySection,
+    notifiers: NotifiersSection,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ChainSection {
+    name: Option<String>,
+    native_symbol: Option<String>,
+    rpc_urls: Option<Vec<String>>,
+    quorum: Option<usize>,
+    ws_url: Option<String>,
+    relay_urls: Option<Vec<String>>,
+    confirmations: Option<u64>,
+    op_stack: Option<bool>,
+    issuers: Option<Vec<String>>,
+    tokens: BTreeMap<String, String>,
+    deployment_blocks: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct IssuerSection {
+    // Ethereum Mainnet only, other chains take token addresses from their `tokens`
+    token: Option<String>,
+    check: Option<String>,
+    freeze_event: Option<String>,
+    unfreeze_event: Option<String>,
+    destroy_event: Option<String>,
+    // Owner functions seen in pending transactions, e.g. "addBlackList(address)"
+    freeze_function: Option<String>,
+    unfreeze_function: Option<String>,
+    decimals: Option<u32>,
+    // Ethereum Mainnet only, other chains use their `deployment_blocks`
+    deployment_block: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MonitorSection {
+    mode: Option<String>,
+    poll_interval_secs: Option<u64>,
+    block_interval_secs: Option<u64>,
+    renotify_interval_secs: Option<u64>,
+    degraded_after_failures: Option<u32>,
+    mempool: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RpcSection {
+    timeout_secs: Option<u64>,
+    health_check_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RetrySection {
+    attempts: Option<u32>,
+    base_delay_ms: Option<u64>,
+    max_delay_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WatchlistSection {
+    default_alerts: Option<String>,
+    // File with one `<address> <label> [alerts=<directions>]` entry per line
+    file: Option<String>,
+    // Comma separated `<address>=<label>[;alerts=<directions>]` entries
+    list: Option<String>,
+    addresses: Vec<WatchAddress>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WatchAddress {
+    address: String,
+    label: Option<String>,
+    alerts: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SweepSection {
+    recipient: Option<String>,
+    // In units of the native token, e.g. "0.01"
+    min_balance: Option<String>,
+    // Left in the account by every sweep, in units of the native token
+    reserve: Option<String>,
+    // Token symbols or addresses swept before the native balance
+    tokens: Vec<String>,
+    interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RescueSection {
+    enabled: Option<bool>,
+    recipient: Option<String>,
+    // In gwei, e.g. "5"
+    priority_fee_gwei: Option<String>,
+    gas_limit: Option<u64>,
+    bump_after_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RelaySection {
+    // bundle (eth_sendBundle) or private_transaction (eth_sendPrivateTransaction)
+    method: Option<String>,
+    target_blocks: Option<u64>,
+    fallback_public: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NotifiersSection {
+    telegram: TelegramSection,
+    templates: TemplatesConfig,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TelegramSection {
+    bot_token: Option<String>,
+    chat_id: Option<String>,
+}
+
+/// Alert message overrides, see `AlertTemplates` for the placeholders.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplatesConfig {
+    pub added: Option<String>,
+    pub removed: Option<String>,
+    pub destroyed: Option<String>,
+    pub reminder: Option<String>,
+    pub pending_added: Option<String>,
+    pub pending_removed: Option<String>,
+    pub reorged_out: Option<String>,
+    pub degraded: Option<String>,
+    pub recovered: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorMode {
+    // Follow freeze logs every block
+    Events,
+    // Only call the freeze status check
+    Poll,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorSettings {
+    pub mode: MonitorMode,
+    pub poll_interval: Duration,
+    pub block_interval: Duration,
+    // Re-send the last alert while an address stays in the same state
+    pub renotify_interval: Option<Duration>,
+    pub degraded_after: u32,
+    // Alert on freeze calls still pending in the mempool of chains with a WebSocket endpoint
+    pub mempool: bool,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct TelegramSettings {
+    pub bot_token: String,
+    pub chat_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepSettings {
+    pub recipient: Option<Address>,
+    pub min_balance: U256,
+    pub reserve: U256,
+    // Symbols or addresses on the chain the sweep runs on
+    pub tokens: Vec<String>,
+    // Time between balance checks of the sweeper service
+    pub interval: Duration,
+}
+
+// Token transfer sent by the monitor when a watched address is removed from a freeze list
+#[derive(Debug, Clone, PartialEq)]
+pub struct RescueSettings {
+    pub enabled: bool,
+    // Required when enabled
+    pub recipient: Option<Address>,
+    // Paid on top of the base fee to land in the next block
+    pub priority_fee: U256,
+    // Gas of the pre-signed transfers, which cannot be estimated while the address is frozen
+    pub gas_limit: U256,
+    // Time a pre-signed transfer is given before it is replaced by the next fee rung
+    pub bump_after: Duration,
+}
+
+// A chain to watch with the freeze lists of its tokens
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainConfig {
+    pub chain: Arc<ChainInfo>,
+    pub issuers: Vec<CheckerSpec>,
+}
+
+/// Validated configuration of every command.
+#[derive(Clone, PartialEq)]
+pub struct Config {
+    // File the configuration was read from, if any
+    pub file: Option<String>,
+    pub database_path: String,
+    pub chain_id: Option<u64>,
+    pub chains: Vec<ChainConfig>,
+    pub monitor: MonitorSettings,
+    pub rpc: RpcSettings,
+    pub relay: RelaySettings,
+    pub retry: RetryPolicy,
+    pub watchlist: Vec<WatchEntry>,
+    // Watched for changes along with the configuration file
+    pub watchlist_file: Option<String>,
+    pub sweep: SweepSettings,
+    pub rescue: RescueSettings,
+    pub telegram: Option<TelegramSettings>,
+    pub templates: AlertTemplates,
+    // Settings that were skipped rather than rejected, reported by the caller
+    pub warnings: Vec<String>,
+}
+
+impl Config {
+    /// Problems that only matter when running the monitor.
+    pub fn monitor_problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.watchlist.is_empty() {
+            problems.push(
+                "watchlist is empty: set watchlist.addresses, watchlist.file or watchlist.list \
+                 (WATCHLIST_FILE, WATCHLIST or TARGET_ADDRESS)"
+                    .to_string(),
+            );
+        }
+        if self.chains.iter().all(|chain| chain.issuers.is_empty()) {
+            problems.push("no freeze list to watch on any chain: set issuers (ISSUERS)".to_string());
+        }
+        if self.telegram.is_none() {
+            problems.push(
+                "missing notifiers.telegram.bot_token and chat_id (TELEGRAM_BOT_TOKEN, TELEGRAM_CHAT_ID)".to_string(),
+            );
+        }
+        problems
+    }
+
+    /// Chain selected for transaction commands: `chain_id`, or the first configured chain.
+    /// None when that chain is not configured, which loading reports as a problem.
+    pub fn transaction_chain(&self) -> Option<&ChainConfig> {
+        select_chain(&self.chains, self.chain_id)
+    }
+}
+
+fn select_chain(chains: &[ChainConfig], chain_id: Option<u64>) -> Option<&ChainConfig> {
+    match chain_id {
+        Some(chain_id) => chains.iter().find(|chain| chain.chain.chain_id == chain_id),
+        None => chains.first(),
+    }
+}
+
+// Every problem found while loading, reported together
+#[derive(Default)]
+pub struct Problems(Vec<String>);
+
+impl Problems {
+    pub fn add(&mut self, problem: impl fmt::Display) {
+        self.0.push(problem.to_string());
+    }
+
+    // Keeps the value of a successful check, records the error otherwise
+    pub fn check<T>(&mut self, result: anyhow::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.add(format!("{:#}", e));
+                None
+            }
+        }
+    }
+}
+
+/// Path of the configuration file: the `--config` flag, `CONFIG_FILE`, or `config.toml`
+/// when it exists.
+pub fn config_path(flag: Option<&str>) -> Option<String> {
+    flag.map(str::to_string)
+        .or_else(|| env_var("CONFIG_FILE"))
+        .or_else(|| Path::new(DEFAULT_CONFIG_FILE).exists().then(|| DEFAULT_CONFIG_FILE.to_string()))
+}
+
+/// Loads the configuration file at `path`, applies environment overrides and validates
+/// the result, failing with every problem found.
+pub fn load(path: Option<&str>) -> crate::error::Result<Config> {
+    let (config, problems) = load_checked(path)?;
+    if !problems.is_empty() {
+        return Err(Error::Config(format_problems(&problems)));
+    }
+    Ok(config)
+}
+
+/// Like `load`, but returns the configuration along with the problems found. Only a
+/// configuration file that cannot be read or parsed fails outright.
+pub fn load_checked(path: Option<&str>) -> crate::error::Result<(Config, Vec<String>)> {
+    let mut file = match path {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .map_err(|e| Error::Config(format!("failed to read {}: {}", path, e)))?;
+            toml::from_str::<FileConfig>(&content).map_err(|e| Error::Config(format!("{}: {}", path, e)))?
+        }
+        None => FileConfig::default(),
+    };
+    let mut problems = Problems::default();
+    apply_env(&mut file, &mut problems);
+    let mut config = resolve(&file, &mut problems);
+    config.file = path.map(str::to_string);
+    Ok((config, problems.0))
+}
+
+/// Resolves the content of a configuration file without environment overrides.
+#[cfg(test)]
+pub fn from_toml(content: &str) -> (Config, Vec<String>) {
+    let file = toml::from_str::<FileConfig>(content).expect("valid configuration file");
+    let mut problems = Problems::default();
+    let config = resolve(&file, &mut problems);
+    (config, problems.0)
+}
+
+fn format_problems(problems: &[String]) -> String {
+    let mut message = format!("{} problem(s)", problems.len());
+    for problem in problems {
+        message.push_str("\n  - ");
+        message.push_str(problem);
+    }
+    message
+}
+
+/// Implements `config check`: validates the configuration, including the monitor settings,
+/// and asks every chain's RPC endpoints for their chain id.
+pub async fn check(path: Option<&str>) -> anyhow::Result<()> {
+    if let Some(path) = path {
+        println!("Configuration file: {}", path);
+    }
+    let (config, mut problems) = load_checked(path)?;
+    problems.extend(config.monitor_problems());
+
+    for chain in &config.chains {
+        let info = &chain.chain;
+        let Ok(client) = ChainClient::connect(info.clone(), config.rpc, config.relay) else { continue };
+        match tokio::time::timeout(config.rpc.timeout, client.provider().get_chainid()).await {
+            Ok(Ok(chain_id)) if chain_id == U256::from(info.chain_id) => {}
+            Ok(Ok(chain_id)) => problems.push(format!(
+                "RPC endpoints of {} report chain id {}, expected {}",
+                info.name, chain_id, info.chain_id
+            )),
+            Ok(Err(e)) => problems.push(format!("cannot reach the RPC endpoints of {}: {}", info.name, e)),
+            Err(_) => problems.push(format!("cannot reach the RPC endpoints of {}: timed out", info.name)),
+        }
+    }
+
+    for chain in &config.chains {
+        let mut issuers: Vec<&str> = chain.issuers.iter().map(|spec| spec.name.as_str()).collect();
+        if issuers.is_empty() {
+            issuers.push("none");
+        }
+        println!(
+            "{} (chain {}): {} RPC endpoint(s), freeze lists {}",
+            chain.chain.name,
+            chain.chain.chain_id,
+            chain.chain.rpc_urls.len(),
+            issuers.join(", ")
+        );
+    }
+    println!("{} watched address(es)", config.watchlist.len());
+    for warning in &config.warnings {
+        println!("Warning: {}", warning);
+    }
+    if !problems.is_empty() {
+        anyhow::bail!("Invalid configuration, {}", format_problems(&problems));
+    }
+    println!("Configuration OK");
+    Ok(())
+}
+
+// Environment variables, with empty values treated as unset
+fn env_var(key: &str) -> Option<String> {
+    env::var(key).ok().map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+fn env_parse<T: FromStr>(key: &str, target: &mut Option<T>, problems: &mut Problems) {
+    if let Some(value) = env_var(key) {
+        match value.parse() {
+            Ok(value) => *target = Some(value),
+            Err(_) => problems.add(format!("invalid {}: {}", key, value)),
+        }
+    }
+}
+
+fn env_string(key: &str, target: &mut Option<String>) {
+    if let Some(value) = env_var(key) {
+        *target = Some(value);
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Suffixes of the ISSUER_<NAME>_* variables, the longer ones first
+const ISSUER_FIELDS: &[&str] = &[
+    "UNFREEZE_FUNCTION",
+    "FREEZE_FUNCTION",
+    "UNFREEZE_EVENT",
+    "FREEZE_EVENT",
+    "DESTROY_EVENT",
+    "DEPLOYMENT_BLOCK",
+    "DECIMALS",
+    "CHECK",
+    "TOKEN",
+];
+
+// Environment variables take precedence over the configuration file
+fn apply_env(file: &mut FileConfig, problems: &mut Problems) {
+    env_string("DATABASE_PATH", &mut file.database_path);
+    env_parse("CHAIN_ID", &mut file.chain_id, problems);
+
+    let monitor = &mut file.monitor;
+    env_string("MONITOR_MODE", &mut monitor.mode);
+    env_parse("POLL_INTERVAL_SECS", &mut monitor.poll_interval_secs, problems);
+    env_parse("BLOCK_INTERVAL_SECS", &mut monitor.block_interval_secs, problems);
+    env_parse("RENOTIFY_INTERVAL_SECS", &mut monitor.renotify_interval_secs, problems);
+    env_parse("DEGRADED_AFTER_FAILURES", &mut monitor.degraded_after_failures, problems);
+    env_parse("MONITOR_MEMPOOL", &mut monitor.mempool, problems);
+    env_parse("RPC_TIMEOUT_SECS", &mut file.rpc.timeout_secs, problems);
+    env_parse("RPC_HEALTH_CHECK_SECS", &mut file.rpc.health_check_secs, problems);
+    env_string("RELAY_METHOD", &mut file.relay.method);
+    env_parse("RELAY_TARGET_BLOCKS", &mut file.relay.target_blocks, problems);
+    env_parse("RELAY_FALLBACK_PUBLIC", &mut file.relay.fallback_public, problems);
+    env_parse("RETRY_ATTEMPTS", &mut file.retry.attempts, problems);
+    env_parse("RETRY_BASE_DELAY_MS", &mut file.retry.base_delay_ms, problems);
+    env_parse("RETRY_MAX_DELAY_SECS", &mut file.retry.max_delay_secs, problems);
+
+    // Chains: CHAINS selects which ones are watched, CHAIN_<ID>_* override their settings
+    let enabled = env_var("CHAINS").map(|ids| split_list(&ids));
+    match &enabled {
+        Some(ids) => {
+            file.chains.retain(|id, _| ids.contains(id));
+            for id in ids {
+                file.chains.entry(id.clone()).or_default();
+            }
+        }
+        None if file.chains.is_empty() => {
+            file.chains.insert("1".to_string(), ChainSection::default());
+        }
+        None => {}
+    }
+    if let Some(mainnet) = file.chains.get_mut("1") {
+        // Variables of the single-chain releases
+        if let Some(urls) = env_var("ETH_RPC_URL").or_else(|| env_var("RPC_URL")) {
+            mainnet.rpc_urls = Some(split_list(&urls));
+        }
+        env_string("WS_PROVIDER", &mut mainnet.ws_url);
+    }
+    let mut vars: Vec<(String, String)> = env::vars().filter(|(_, value)| !value.trim().is_empty()).collect();
+    vars.sort();
+    for (key, value) in &vars {
+        let value = value.trim();
+        if let Some(rest) = key.strip_prefix("CHAIN_") {
+            let Some((id, field)) = rest.split_once('_') else { continue };
+            if id.parse::<u64>().is_err() {
+                continue;
+            }
+            // Settings of chains that are not watched are ignored
+            let Some(chain) = file.chains.get_mut(id) else { continue };
+            match field {
+                "NAME" => chain.name = Some(value.to_string()),
+                "NATIVE_SYMBOL" => chain.native_symbol = Some(value.to_string()),
+                "RPC_URLS" => chain.rpc_urls = Some(split_list(value)),
+                "WS_URL" => chain.ws_url = Some(value.to_string()),
+                "RELAY_URLS" => chain.relay_urls = Some(split_list(value)),
+                "ISSUERS" => chain.issuers = Some(split_list(value)),
+                "QUORUM" => env_parse(key, &mut chain.quorum, problems),
+                "CONFIRMATIONS" => env_parse(key, &mut chain.confirmations, problems),
+                "OP_STACK" => env_parse(key, &mut chain.op_stack, problems),
+                _ => {
+                    if let Some(symbol) = field.strip_prefix("TOKEN_") {
+                        chain.tokens.insert(symbol.to_uppercase(), value.to_string());
+                    } else if let Some(name) = field.strip_prefix("DEPLOYMENT_BLOCK_") {
+                        match value.parse() {
+                            Ok(block) => {
+                                chain.deployment_blocks.insert(name.to_uppercase(), block);
+                            }
+                            Err(_) => problems.add(format!("invalid {}: {}", key, value)),
+                        }
+                    } else {
+                        problems.add(format!("unknown setting {}", key));
+                    }
+                }
+            }
+        } else if let Some(rest) = key.strip_prefix("ISSUER_") {
+            let Some((name, field)) = ISSUER_FIELDS
+                .iter()
+                .find_map(|field| rest.strip_suffix(&format!("_{}", field)).map(|name| (name, *field)))
+            else {
+                problems.add(format!("unknown setting {}", key));
+                continue;
+            };
+            let issuer = file.issuer.entry(name.to_uppercase()).or_default();
+            match field {
+                "TOKEN" => issuer.token = Some(value.to_string()),
+                "CHECK" => issuer.check = Some(value.to_string()),
+                "FREEZE_EVENT" => issuer.freeze_event = Some(value.to_string()),
+                "UNFREEZE_EVENT" => issuer.unfreeze_event = Some(value.to_string()),
+                "DESTROY_EVENT" => issuer.destroy_event = Some(value.to_string()),
+                "FREEZE_FUNCTION" => issuer.freeze_function = Some(value.to_string()),
+                "UNFREEZE_FUNCTION" => issuer.unfreeze_function = Some(value.to_string()),
+                "DECIMALS" => env_parse(key, &mut issuer.decimals, problems),
+                _ => env_parse(key, &mut issuer.deployment_block, problems),
+            }
+        }
+    }
+    if let Some(issuers) = env_var("ISSUERS") {
+        file.issuers = Some(split_list(&issuers));
+    }
+
+    // A watchlist source from the environment replaces the one of the file
+    let watchlist = &mut file.watchlist;
+    env_string("DEFAULT_ALERTS", &mut watchlist.default_alerts);
+    if let Some(path) = env_var("WATCHLIST_FILE") {
+        *watchlist = WatchlistSection { file: Some(path), default_alerts: watchlist.default_alerts.take(), ..Default::default() };
+    } else if let Some(list) = env_var("WATCHLIST") {
+        *watchlist = WatchlistSection { list: Some(list), default_alerts: watchlist.default_alerts.take(), ..Default::default() };
+    } else if let Some(target) = env_var("TARGET_ADDRESS") {
+        *watchlist = WatchlistSection {
+            addresses: vec![WatchAddress { address: target, label: Some("target".to_string()), alerts: None }],
+            default_alerts: watchlist.default_alerts.take(),
+            ..Default::default()
+        };
+    }
+
+    env_string("RECIPIENT_ADDRESS", &mut file.sweep.recipient);
+    env_string("MIN_BALANCE_TO_TRANSFER", &mut file.sweep.min_balance);
+    env_string("SWEEP_RESERVE", &mut file.sweep.reserve);
+    if let Some(tokens) = env_var("SWEEP_TOKENS") {
+        file.sweep.tokens = split_list(&tokens);
+    }
+    env_parse("SWEEP_INTERVAL_SECS", &mut file.sweep.interval_secs, problems);
+    env_parse("RESCUE_ENABLED", &mut file.rescue.enabled, problems);
+    env_string("RESCUE_RECIPIENT", &mut file.rescue.recipient);
+    env_string("RESCUE_PRIORITY_FEE_GWEI", &mut file.rescue.priority_fee_gwei);
+    env_parse("RESCUE_GAS_LIMIT", &mut file.rescue.gas_limit, problems);
+    env_parse("RESCUE_BUMP_AFTER_SECS", &mut file.rescue.bump_after_secs, problems);
+
+    let telegram = &mut file.notifiers.telegram;
+    if let Some(token) = env_var("TELEGRAM_BOT_TOKEN").or_else(|| env_var("BOT_TOKEN")) {
+        telegram.bot_token = Some(token);
+    }
+    if let Some(chat_id) = env_var("TELEGRAM_CHAT_ID").or_else(|| env_var("CHAT_ID")) {
+        telegram.chat_id = Some(chat_id);
+    }
+    let templates = &mut file.notifiers.templates;
+    env_string("ALERT_TEMPLATE_ADDED", &mut templates.added);
+    env_string("ALERT_TEMPLATE_REMOVED", &mut templates.removed);
+    env_string("ALERT_TEMPLATE_DESTROYED", &mut templates.destroyed);
+    env_string("ALERT_TEMPLATE_REMINDER", &mut templates.reminder);
+    env_string("ALERT_TEMPLATE_PENDING_ADDED", &mut templates.pending_added);
+    env_string("ALERT_TEMPLATE_PENDING_REMOVED", &mut templates.pending_removed);
+    env_string("ALERT_TEMPLATE_REORGED_OUT", &mut templates.reorged_out);
+    env_string("ALERT_TEMPLATE_DEGRADED", &mut templates.degraded);
+    env_string("ALERT_TEMPLATE_RECOVERED", &mut templates.recovered);
+}
+
+fn resolve(file: &FileConfig, problems: &mut Problems) -> Config {
+    let monitor = &file.monitor;
+    let mode = match monitor.mode.as_deref().unwrap_or("events") {
+        "events" => MonitorMode::Events,
+        "poll" => MonitorMode::Poll,
+        other => {
+            problems.add(format!("unknown monitor.mode {} (MONITOR_MODE), expected events or poll", other));
+            MonitorMode::Events
+        }
+    };
+    let renotify_secs = monitor.renotify_interval_secs.unwrap_or(0);
+    let monitor = MonitorSettings {
+        mode,
+        poll_interval: Duration::from_secs(monitor.poll_interval_secs.unwrap_or(60)),
+        block_interval: Duration::from_secs(monitor.block_interval_secs.unwrap_or(4)),
+        renotify_interval: (renotify_secs > 0).then(|| Duration::from_secs(renotify_secs)),
+        degraded_after: monitor.degraded_after_failures.unwrap_or(5).max(1),
+        mempool: monitor.mempool.unwrap_or(false),
+    };
+    let rpc = RpcSettings {
+        timeout: Duration::from_secs(file.rpc.timeout_secs.unwrap_or(10)),
+        health_check_interval: Duration::from_secs(file.rpc.health_check_secs.unwrap_or(30)),
+    };
+    let relay = RelaySettings {
+        method: match file.relay.method.as_deref().unwrap_or("private_transaction") {
+            "bundle" => RelayMethod::Bundle,
+            "private_transaction" => RelayMethod::PrivateTransaction,
+            other => {
+                problems.add(format!("unknown relay.method {} (RELAY_METHOD), expected bundle or private_transaction", other));
+                RelayMethod::PrivateTransaction
+            }
+        },
+        target_blocks: file.relay.target_blocks.unwrap_or(3).max(1),
+        fallback_public: file.relay.fallback_public.unwrap_or(true),
+    };
+    let retry = RetryPolicy {
+        attempts: file.retry.attempts.unwrap_or(4).max(1),
+        base_delay: Duration::from_millis(file.retry.base_delay_ms.unwrap_or(500)),
+        max_delay: Duration::from_secs(file.retry.max_delay_secs.unwrap_or(30)),
+    };
+
+    let mut chains = Vec::new();
+    let mut warnings = Vec::new();
+    for (id, section) in &file.chains {
+        let Ok(chain_id) = id.parse::<u64>() else {
+            problems.add(format!("invalid chain id {}", id));
+            continue;
+        };
+        let Some(chain) = resolve_chain(chain_id, section, problems) else { continue };
+        let chain = Arc::new(chain);
+        // By default USDT where it has a freeze list, otherwise the built-in tokens that do
+        let names = section.issuers.clone().or_else(|| file.issuers.clone()).unwrap_or_else(|| {
+            if chains::builtin_freeze_list(chain_id, "USDT") != Some(None) {
+                vec!["USDT".to_string()]
+            } else {
+                chains::builtin_freeze_tokens(chain_id).into_iter().map(str::to_string).collect()
+            }
+        });
+        let mut issuers = Vec::new();
+        for name in names {
+            let name = name.to_uppercase();
+            let deployment_block = section.deployment_blocks.get(&name).copied();
+            let Some(spec) = resolve_issuer(&name, &chain, file.issuer.get(&name), deployment_block, problems, &mut warnings)
+            else {
+                continue;
+            };
+            // Parsing the signatures is the validation
+            if problems.check(SignatureChecker::new(spec.clone(), chain.clone()).with_context(|| {
+                format!("invalid freeze list {} on {}", name, chain.name)
+            })).is_some() {
+                issuers.push(spec);
+            }
+        }
+        // Still usable by the transaction commands
+        if issuers.is_empty() {
+            warnings.push(format!("no freeze list to watch on {}: set chains.{}.issuers (CHAIN_{}_ISSUERS)", chain.name, chain_id, chain_id));
+        }
+        chains.push(ChainConfig { chain, issuers });
+    }
+    if file.chains.is_empty() {
+        problems.add("no chain configured: set chains (CHAINS)");
+    }
+    if let Some(chain_id) = file.chain_id {
+        if !chains.iter().any(|chain| chain.chain.chain_id == chain_id) {
+            problems.add(format!("chain_id {} (CHAIN_ID) is not one of the configured chains", chain_id));
+        }
+    }
+
+    let defaults = problems
+        .check(
+            AlertDirections::parse(file.watchlist.default_alerts.as_deref().unwrap_or("remove"))
+                .context("invalid watchlist.default_alerts (DEFAULT_ALERTS)"),
+        )
+        .unwrap_or(AlertDirections { on_add: false, on_remove: true, on_destroy: false });
+    let mut entries = Vec::new();
+    if let Some(path) = &file.watchlist.file {
+        if let Some(parsed) = problems.check(watchlist::read_file(path, defaults)) {
+            entries.extend(parsed.into_iter().filter_map(|entry| problems.check(entry)));
+        }
+    }
+    if let Some(list) = &file.watchlist.list {
+        for entry in watchlist::parse_list(list, defaults) {
+            entries.extend(problems.check(entry.context("invalid watchlist.list (WATCHLIST) entry")));
+        }
+    }
+    for entry in &file.watchlist.addresses {
+        let entry = watchlist::parse_entry(&entry.address, entry.label.as_deref().unwrap_or(""), entry.alerts.as_deref(), defaults)
+            .context("invalid watchlist.addresses entry");
+        entries.extend(problems.check(entry));
+    }
+
+    let sweep = SweepSettings {
+        recipient: file.sweep.recipient.as_deref().and_then(|recipient| {
+            problems.check(
+                recipient
+                    .parse()
+                    .with_context(|| format!("invalid sweep.recipient (RECIPIENT_ADDRESS) {}", recipient)),
+            )
+        }),
+        min_balance: problems
+            .check(
+                ethers::utils::parse_ether(file.sweep.min_balance.as_deref().unwrap_or("0.01"))
+                    .context("invalid sweep.min_balance (MIN_BALANCE_TO_TRANSFER)"),
+            )
+            .unwrap_or_default(),
+        reserve: problems
+            .check(
+                ethers::utils::parse_ether(file.sweep.reserve.as_deref().unwrap_or("0"))
+                    .context("invalid sweep.reserve (SWEEP_RESERVE)"),
+            )
+            .unwrap_or_default(),
+        tokens: file.sweep.tokens.clone(),
+        interval: Duration::from_secs(file.sweep.interval_secs.unwrap_or(60).max(1)),
+    };
+    // Checked on the default transaction chain; --chain picks another one at run time
+    if let Some(chain) = select_chain(&chains, file.chain_id) {
+        for token in &sweep.tokens {
+            if chain.chain.token(token).is_none() && token.parse::<Address>().is_err() {
+                problems.add(format!("unknown sweep.tokens (SWEEP_TOKENS) entry {} on {}", token, chain.chain.name));
+            }
+        }
+    }
+    for key in PRIVATE_KEY_VARS {
+        if env_var(key).is_some_and(|value| client::parse_wallet(&value, key).is_err()) {
+            problems.add(format!("{} is not a valid private key", key));
+        }
+    }
+
+    let rescue = RescueSettings {
+        enabled: file.rescue.enabled.unwrap_or(false),
+        recipient: file.rescue.recipient.as_deref().and_then(|recipient| {
+            problems.check(
+                recipient
+                    .parse()
+                    .with_context(|| format!("invalid rescue.recipient (RESCUE_RECIPIENT) {}", recipient)),
+            )
+        }),
+        priority_fee: problems
+            .check(
+                ethers::utils::parse_units(file.rescue.priority_fee_gwei.as_deref().unwrap_or("5"), "gwei")
+                    .map(U256::from)
+                    .context("invalid rescue.priority_fee_gwei (RESCUE_PRIORITY_FEE_GWEI)"),
+            )
+            .unwrap_or_default(),
+        gas_limit: U256::from(file.rescue.gas_limit.unwrap_or(100_000)),
+        bump_after: Duration::from_secs(file.rescue.bump_after_secs.unwrap_or(12).max(1)),
+    };
+    let rescue_keys = env_var(RESCUE_KEYS_VAR).map(|keys| split_list(&keys)).unwrap_or_default();
+    if rescue_keys.iter().any(|key| client::parse_wallet(key, RESCUE_KEYS_VAR).is_err()) {
+        problems.add(format!("{} holds an invalid private key", RESCUE_KEYS_VAR));
+    }
+    if rescue.enabled {
+        if rescue.recipient.is_none() {
+            problems.add("rescue.enabled needs rescue.recipient (RESCUE_RECIPIENT)");
+        }
+        if rescue_keys.is_empty() {
+            problems.add(format!("rescue.enabled needs the keys of the rescued addresses in {}", RESCUE_KEYS_VAR));
+        }
+    }
+
+    let telegram = &file.notifiers.telegram;
+    let telegram = match (&telegram.bot_token, &telegram.chat_id) {
+        (Some(bot_token), Some(chat_id)) => Some(TelegramSettings { bot_token: bot_token.clone(), chat_id: chat_id.clone() }),
+        (None, None) => None,
+        (Some(_), None) => {
+            problems.add("notifiers.telegram.chat_id (TELEGRAM_CHAT_ID) is missing");
+            None
+        }
+        (None, Some(_)) => {
+            problems.add("notifiers.telegram.bot_token (TELEGRAM_BOT_TOKEN) is missing");
+            None
+        }
+    };
+
+    Config {
+        file: None,
+        database_path: file.database_path.clone().unwrap_or_else(|| "blacklist_monitor.db".to_string()),
+        chain_id: file.chain_id,
+        chains,
+        monitor,
+        rpc,
+        relay,
+        retry,
+        watchlist: watchlist::dedup(entries),
+        watchlist_file: file.watchlist.file.clone(),
+        sweep,
+        rescue,
+        telegram,
+        templates: AlertTemplates::from_config(&file.notifiers.templates),
+        warnings,
+    }
+}
+
+fn resolve_chain(chain_id: u64, section: &ChainSection, problems: &mut Problems) -> Option<ChainInfo> {
+    let prefix = format!("chains.{}", chain_id);
+    let mut chain = match chains::builtin(chain_id) {
+        Some(chain) => chain,
+        None => {
+            let (Some(name), Some(native_symbol)) = (&section.name, &section.native_symbol) else {
+                problems.add(format!(
+                    "chain {} is not built in and needs {}.name and {}.native_symbol (CHAIN_{}_NAME, CHAIN_{}_NATIVE_SYMBOL)",
+                    chain_id, prefix, prefix, chain_id, chain_id
+                ));
+                return None;
+            };
+            ChainInfo {
+                chain_id,
+                name: name.clone(),
+                rpc_urls: Vec::new(),
+                quorum: 1,
+                ws_url: None,
+                relay_urls: Vec::new(),
+                native_symbol: native_symbol.clone(),
+                tokens: Default::default(),
+                confirmations: 1,
+                op_stack: false,
+            }
+        }
+    };
+    if let Some(name) = &section.name {
+        chain.name = name.clone();
+    }
+    if let Some(symbol) = &section.native_symbol {
+        chain.native_symbol = symbol.clone();
+    }
+
+    chain.rpc_urls = section.rpc_urls.clone().unwrap_or_default();
+    if chain.rpc_urls.is_empty() {
+        let legacy = if chain_id == 1 { ", ETH_RPC_URL" } else { "" };
+        problems.add(format!("{} has no RPC endpoint: set {}.rpc_urls (CHAIN_{}_RPC_URLS{})", chain.name, prefix, chain_id, legacy));
+    }
+    for (index, url) in chain.rpc_urls.iter().enumerate() {
+        if rpc::parse_url(url).is_none() {
+            problems.add(format!("{}.rpc_urls has an invalid URL at #{}", prefix, index + 1));
+        }
+    }
+    if let Some(quorum) = section.quorum {
+        if quorum == 0 || quorum > chain.rpc_urls.len() {
+            problems.add(format!(
+                "{}.quorum must be between 1 and the number of RPC URLs ({})",
+                prefix,
+                chain.rpc_urls.len()
+            ));
+        } else {
+            chain.quorum = quorum;
+        }
+    }
+    if let Some(ws_url) = &section.ws_url {
+        if rpc::parse_url(ws_url).is_none() {
+            problems.add(format!("{}.ws_url is not a valid URL", prefix));
+        }
+        chain.ws_url = Some(ws_url.clone());
+    }
+    chain.relay_urls = section.relay_urls.clone().unwrap_or_default();
+    for (index, url) in chain.relay_urls.iter().enumerate() {
+        if rpc::parse_url(url).is_none() {
+            problems.add(format!("{}.relay_urls has an invalid URL at #{}", prefix, index + 1));
+        }
+    }
+    if let Some(confirmations) = section.confirmations {
+        chain.confirmations = confirmations;
+    }
+    if let Some(op_stack) = section.op_stack {
+        chain.op_stack = op_stack;
+    }
+    for (symbol, address) in &section.tokens {
+        let address = address
+            .parse()
+            .with_context(|| format!("invalid {}.tokens.{} {}", prefix, symbol, address));
+        if let Some(address) = problems.check(address) {
+            chain.tokens.insert(symbol.to_uppercase(), address);
+        }
+    }
+    Some(chain)
+}
+
+// Built-in issuers start from their defaults and every setting is an optional override;
+// other issuers must define their check function and freeze events
+fn resolve_issuer(
+    name: &str,
+    chain: &ChainInfo,
+    section: Option<&IssuerSection>,
+    deployment_block: Option<u64>,
+    problems: &mut Problems,
+    warnings: &mut Vec<String>,
+) -> Option<CheckerSpec> {
+    let empty = IssuerSection::default();
+    let section = section.unwrap_or(&empty);
+    let mainnet = chain.chain_id == 1;
+
+    let token = match section.token.as_deref().filter(|_| mainnet) {
+        Some(token) => problems.check(
+            token
+                .parse()
+                .with_context(|| format!("invalid issuer.{}.token (ISSUER_{}_TOKEN) {}", name, name, token)),
+        )?,
+        None => match chain.token(name) {
+            Some(token) => token,
+            None => {
+                warnings.push(format!("no {} token address known on {}, skipping it", name, chain.name));
+                return None;
+            }
+        },
+    };
+    // Built-in chains know the freeze list of their tokens, other chains are assumed to
+    // hold USDT0 and Circle's native USDC
+    let list = match chains::builtin_freeze_list(chain.chain_id, name) {
+        Some(list) => list,
+        None if chains::builtin(chain.chain_id).is_some() => None,
+        None => match name {
+            "USDT" => Some(FreezeList::TetherV2),
+            "USDC" => Some(FreezeList::FiatToken),
+            _ => None,
+        },
+    };
+    let mut spec = match list {
+        Some(list) => CheckerSpec::builtin(name, list, token),
+        None if chains::builtin_freeze_list(chain.chain_id, name).is_some() && section.check.is_none() => {
+            problems.add(format!(
+                "{} on {} has no freeze list: set chains.{}.issuers without it (CHAIN_{}_ISSUERS)",
+                name, chain.name, chain.chain_id, chain.chain_id
+            ));
+            return None;
+        }
+        None => {
+            let mut missing = Vec::new();
+            for (field, value) in [
+                ("check", &section.check),
+                ("freeze_event", &section.freeze_event),
+                ("unfreeze_event", &section.unfreeze_event),
+            ] {
+                if value.is_none() {
+                    missing.push(format!("issuer.{}.{} (ISSUER_{}_{})", name, field, name, field.to_uppercase()));
+                }
+            }
+            if !missing.is_empty() {
+                problems.add(format!("freeze list {} needs {}", name, missing.join(", ")));
+                return None;
+            }
+            CheckerSpec {
+                name: name.to_string(),
+                token,
+                decimals: 18,
+                deployment_block: 0,
+                check_function: String::new(),
+                freeze_event: String::new(),
+                unfreeze_event: String::new(),
+                destroy_event: None,
+                freeze_function: None,
+                unfreeze_function: None,
+            }
+        }
+    };
+    spec.token = token;
+    // Built-in deployment blocks are those of the Ethereum Mainnet contracts
+    if !mainnet {
+        spec.deployment_block = 0;
+    }
+    if let Some(check) = &section.check {
+        spec.check_function = check.clone();
+    }
+    if let Some(event) = &section.freeze_event {
+        spec.freeze_event = event.clone();
+    }
+    if let Some(event) = &section.unfreeze_event {
+        spec.unfreeze_event = event.clone();
+    }
+    if let Some(event) = &section.destroy_event {
+        spec.destroy_event = Some(event.clone());
+    }
+    if let Some(function) = &section.freeze_function {
+        spec.freeze_function = Some(function.clone());
+    }
+    if let Some(function) = &section.unfreeze_function {
+        spec.unfreeze_function = Some(function.clone());
+    }
+    if let Some(decimals) = section.decimals {
+        spec.decimals = decimals;
+    }
+    if let Some(block) = deployment_block.or(section.deployment_block.filter(|_| mainnet)) {
+        spec.deployment_block = block;
+    }
+    Some(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // Loading reads the whole environment, so tests that change it take turns
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn from_toml_and_env(content: &str, vars: &[(&str, &str)]) -> (Config, Vec<String>) {
+        let _turn = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        for (key, value) in vars {
+            env::set_var(key, value);
+        }
+        let mut file = toml::from_str::<FileConfig>(content).unwrap();
+        let mut problems = Problems::default();
+        apply_env(&mut file, &mut problems);
+        let config = resolve(&file, &mut problems);
+        for (key, _) in vars {
+            env::remove_var(key);
+        }
+        (config, problems.0)
+    }
+
+    #[test]
+    fn reports_every_problem_at_once() {
+        let (config, problems) = from_toml(
+            r#"
+            chain_id = 5
+
+            [chains.1]
+            rpc_urls = ["not a url"]
+            quorum = 2
+
+            [chains.777]
+            rpc_urls = ["https://rpc.example.org"]
+
+            [monitor]
+            mode = "fast"
+
+            [sweep]
+            recipient = "0x123"
+
+            [rescue]
+            enabled = true
+
+            [notifiers.telegram]
+            bot_token = "token"
+            "#,
+        );
+        let expected = [
+            "chains.1.rpc_urls has an invalid URL at #1",
+            "chains.1.quorum must be between 1 and the number of RPC URLs (1)",
+            "chain 777 is not built in and needs chains.777.name and chains.777.native_symbol",
+            "chain_id 5 (CHAIN_ID) is not one of the configured chains",
+            "unknown monitor.mode fast (MONITOR_MODE)",
+            "invalid sweep.recipient (RECIPIENT_ADDRESS) 0x123",
+            "rescue.enabled needs rescue.recipient (RESCUE_RECIPIENT)",
+            "rescue.enabled needs the keys of the rescued addresses in RESCUE_PRIVATE_KEYS",
+            "notifiers.telegram.chat_id (TELEGRAM_CHAT_ID) is missing",
+        ];
+        for problem in expected {
+            assert!(problems.iter().any(|p| p.starts_with(problem)), "missing {:?} in {:#?}", problem, problems);
+        }
+        assert_eq!(problems.len(), expected.len(), "{:#?}", problems);
+        assert!(config.transaction_chain().is_none());
+    }
+
+    #[test]
+    fn selects_the_transaction_chain() {
+        let content = r#"
+            [chains.1]
+            rpc_urls = ["https://mainnet.example.org"]
+
+            [chains.10]
+            rpc_urls = ["https://optimism.example.org"]
+            "#;
+        let (config, problems) = from_toml(content);
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(config.transaction_chain().unwrap().chain.chain_id, 1);
+
+        let (config, problems) = from_toml(&format!("chain_id = 10\n{}", content));
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(config.transaction_chain().unwrap().chain.chain_id, 10);
+    }
+
+    #[test]
+    fn chain_and_issuer_variables_override_the_file() {
+        let (config, problems) = from_toml_and_env(
+            r#"
+            [chains.42161]
+            rpc_urls = ["https://file.example.org"]
+            "#,
+            &[
+                ("CHAIN_42161_RPC_URLS", "https://a.example.org, https://b.example.org"),
+                ("CHAIN_42161_QUORUM", "2"),
+                ("CHAIN_42161_CONFIRMATIONS", "20"),
+                ("CHAIN_42161_ISSUERS", "usdc"),
+                ("CHAIN_42161_COLOR", "blue"),
+                // Settings of chains that are not watched are ignored
+                ("CHAIN_10_RPC_URLS", "https://optimism.example.org"),
+                ("ISSUER_USDC_DECIMALS", "8"),
+                ("ISSUER_USDC_COLOR", "blue"),
+            ],
+        );
+        assert_eq!(problems, ["unknown setting CHAIN_42161_COLOR", "unknown setting ISSUER_USDC_COLOR"]);
+        assert_eq!(config.chains.len(), 1);
+        let chain = &config.chains[0];
+        assert_eq!(chain.chain.rpc_urls, ["https://a.example.org", "https://b.example.org"]);
+        assert_eq!(chain.chain.quorum, 2);
+        assert_eq!(chain.chain.confirmations, 20);
+        assert_eq!(chain.issuers.len(), 1);
+        assert_eq!(chain.issuers[0].name, "USDC");
+        assert_eq!(chain.issuers[0].decimals, 8);
+    }
+
+    #[test]
+    fn single_chain_variables_configure_mainnet() {
+        let (config, problems) = from_toml_and_env(
+            "",
+            &[("ETH_RPC_URL", "https://legacy.example.org"), ("WS_PROVIDER", "wss://legacy.example.org")],
+        );
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(config.chains.len(), 1);
+        let chain = &config.chains[0].chain;
+        assert_eq!(chain.chain_id, 1);
+        assert_eq!(chain.rpc_urls, ["https://legacy.example.org"]);
+        assert_eq!(chain.ws_url.as_deref(), Some("wss://legacy.example.org"));
+
+        // The variables of the chain take precedence
+        let (config, _) = from_toml_and_env(
+            "",
+            &[("ETH_RPC_URL", "https://legacy.example.org"), ("CHAIN_1_RPC_URLS", "https://mainnet.example.org")],
+        );
+        assert_eq!(config.chains[0].chain.rpc_urls, ["https://mainnet.example.org"]);
+    }
+
+    #[test]
+    fn an_empty_chain_list_is_a_problem() {
+        let (config, problems) = from_toml_and_env("", &[("CHAINS", ",")]);
+        assert!(problems.contains(&"no chain configured: set chains (CHAINS)".to_string()), "{:?}", problems);
+        assert!(config.transaction_chain().is_none());
+    }
+}
@@ -0,0 +1,35 @@
+use ethers::prelude::abigen;
+
+// Multicall3 is deployed at the same address on Ethereum and most EVM chains
+pub const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+
+abigen!(
+    Multicall3,
+    r#"[
+        struct Call3 { address target; bool allowFailure; bytes callData; }
+        struct Call3Result { bool success; bytes returnData; }
+        function aggregate3(Call3[] calls) payable returns (Call3Result[] returnData)
+    ]"#,
+);
+
+// USDT's transfer functions do not return a value, so only send them, never `call` them
+abigen!(
+    Erc20,
+    r#"[
+        function balanceOf(address owner) view returns (uint256)
+        function allowance(address owner, address spender) view returns (uint256)
+        function transfer(address to, uint256 value) returns (bool)
+        function transferFrom(address from, address to, uint256 value) returns (bool)
+        function approve(address spender, uint256 value) returns (bool)
+    ]"#,
+);
+
+// Predeploy of OP Stack rollups that quotes the L1 data fee of a transaction
+pub const GAS_PRICE_ORACLE_ADDRESS: &str = "0x420000000000000000000000000000000000000F";
+
+abigen!(
+    GasPriceOracle,
+    r#"[
+        function getL1Fee(bytes data) view returns (uint256)
+    ]"#,
+);
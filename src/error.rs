@@ -0,0 +1,204 @@
+use ethers::contract::{ContractError, EthError};
+use ethers::middleware::signer::SignerMiddlewareError;
+use ethers::prelude::*;
+use ethers::providers::{JsonRpcError, RpcError};
+use std::sync::Arc;
+
+use crate::client::ChainProvider;
+use crate::retry::{self, ErrorClass};
+
+/// Errors of the chain client, transactions and notifications, sorted by what the
+/// caller can do about them.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    // The node could not be reached or answered with an unexpected error
+    #[error("RPC error: {0}")]
+    Rpc(#[source] ProviderError),
+    // The node answered with data that does not match the contract ABI
+    #[error("unexpected contract response: {0}")]
+    InvalidResponse(String),
+    // The endpoint has not seen the block yet, usually because it lags behind another one
+    #[error("block not found: {0}")]
+    BlockNotFound(String),
+    #[error("transaction reverted: {reason}")]
+    Revert { reason: String },
+    #[error("insufficient funds: {0}")]
+    InsufficientFunds(String),
+    // Checked before sending, so nothing was spent on a transfer that would revert
+    #[error("allowance of {spender:?} from {owner:?} is {allowance}, below {amount}")]
+    InsufficientAllowance { owner: Address, spender: Address, allowance: U256, amount: U256 },
+    // Nonce already used, too high, or the transaction was replaced
+    #[error("nonce error: {0}")]
+    Nonce(String),
+    #[error("signing failed: {0}")]
+    Signing(String),
+    // No private relay took the transaction, or it was not included in time
+    #[error("relay error: {0}")]
+    Relay(String),
+    #[error("notification failed: {0}")]
+    Notification(#[source] reqwest::Error),
+    // The notifier answered, but did not take the message
+    #[error("notification rejected with HTTP {status}: {description}")]
+    NotificationRejected { status: u16, description: String },
+    #[error("invalid configuration: {0}")]
+    Config(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Builds a revert error from ABI encoded revert data, decoding `Error(string)` reasons.
+    pub fn revert(data: &Bytes) -> Self {
+        let reason = match String::decode_with_selector(data) {
+            Some(reason) => reason,
+            None if data.is_empty() => "no reason given".to_string(),
+            None => format!("data {}", data),
+        };
+        Error::Revert { reason }
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Error::Rpc(error) => retry::classify_provider(error),
+            Error::Notification(error) => retry::classify_http(error),
+            Error::NotificationRejected { status, .. } if *status == 429 || *status >= 500 => ErrorClass::Transient,
+            // A fresh nonce is fetched when the transaction is built again
+            Error::Nonce(_) => ErrorClass::Transient,
+            // Submitted again for later blocks
+            Error::Relay(_) => ErrorClass::Transient,
+            Error::BlockNotFound(_) => ErrorClass::Transient,
+            Error::InvalidResponse(_)
+            | Error::Revert { .. }
+            | Error::InsufficientFunds(_)
+            | Error::InsufficientAllowance { .. }
+            | Error::NotificationRejected { .. }
+            | Error::Signing(_)
+            | Error::Config(_) => ErrorClass::Fatal,
+        }
+    }
+
+    // Recognises node answers about the transaction itself rather than the node
+    fn from_response(response: &JsonRpcError) -> Option<Self> {
+        if let Some(data) = response.as_revert_data() {
+            return Some(Error::revert(&data));
+        }
+        let message = response.message.to_lowercase();
+        if message.contains("insufficient funds") {
+            Some(Error::InsufficientFunds(response.message.clone()))
+        } else if ["nonce", "replacement transaction underpriced", "already known"]
+            .iter()
+            .any(|m| message.contains(m))
+        {
+            Some(Error::Nonce(response.message.clone()))
+        } else {
+            None
+        }
+    }
+}
+
+impl From<ProviderError> for Error {
+    fn from(error: ProviderError) -> Self {
+        match RpcError::as_error_response(&error).and_then(Error::from_response) {
+            Some(error) => error,
+            None => Error::Rpc(error),
+        }
+    }
+}
+
+impl From<SignerMiddlewareError<Arc<ChainProvider>, LocalWallet>> for Error {
+    fn from(error: SignerMiddlewareError<Arc<ChainProvider>, LocalWallet>) -> Self {
+        match error {
+            SignerMiddlewareError::MiddlewareError(error) => error.into(),
+            SignerMiddlewareError::SignerError(error) => Error::Signing(error.to_string()),
+            SignerMiddlewareError::NonceMissing => Error::Nonce(error.to_string()),
+            SignerMiddlewareError::DifferentChainID => Error::Config(error.to_string()),
+            error => Error::Signing(error.to_string()),
+        }
+    }
+}
+
+impl<M: Middleware> From<ContractError<M>> for Error
+where
+    Error: From<M::Error>,
+{
+    fn from(error: ContractError<M>) -> Self {
+        match error {
+            ContractError::Revert(data) => Error::revert(&data),
+            ContractError::MiddlewareError { e } => e.into(),
+            ContractError::ProviderError { e } => e.into(),
+            error => Error::InvalidResponse(error.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{self, Token};
+    use ethers::providers::HttpClientError;
+    use serde_json::json;
+
+    use crate::testing::{self, calls, rpc_server, RpcHandler};
+    use crate::transfer;
+
+    fn response(code: i64, message: &str, data: Option<serde_json::Value>) -> Error {
+        let error = JsonRpcError { code, message: message.to_string(), data };
+        ProviderError::JsonRpcClientError(Box::new(HttpClientError::JsonRpcError(error))).into()
+    }
+
+    #[test]
+    fn decodes_revert_reasons() {
+        let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+        data.extend(abi::encode(&[Token::String("Blacklistable: account is blacklisted".to_string())]));
+        let error = response(3, "execution reverted", Some(json!(Bytes::from(data))));
+        assert!(matches!(&error, Error::Revert { reason } if reason == "Blacklistable: account is blacklisted"));
+        assert_eq!(error.class(), ErrorClass::Fatal);
+
+        assert!(matches!(Error::revert(&Bytes::new()), Error::Revert { reason } if reason == "no reason given"));
+        assert!(matches!(Error::revert(&Bytes::from(vec![0xde, 0xad])), Error::Revert { reason } if reason == "data 0xdead"));
+    }
+
+    #[test]
+    fn maps_node_answers_about_the_transaction() {
+        let funds = response(-32000, "Insufficient funds for gas * price + value", None);
+        assert!(matches!(funds, Error::InsufficientFunds(_)));
+        assert_eq!(funds.class(), ErrorClass::Fatal);
+
+        for message in ["nonce too low", "replacement transaction underpriced", "already known"] {
+            let error = response(-32000, message, None);
+            assert!(matches!(&error, Error::Nonce(m) if m == message), "{}", message);
+            assert_eq!(error.class(), ErrorClass::Transient);
+        }
+
+        // Anything else stays an error of the node
+        let other = response(-32000, "header not found", None);
+        assert!(matches!(other, Error::Rpc(_)));
+        assert_eq!(other.class(), ErrorClass::Transient);
+    }
+
+    #[tokio::test]
+    async fn refuses_a_transfer_from_above_the_allowance() {
+        let handler: Arc<RpcHandler> = Arc::new(|method, _| match method {
+            "eth_chainId" => Ok(json!("0x1")),
+            // allowance() of 100
+            "eth_call" => Ok(json!(format!("0x{:064x}", 100))),
+            _ => Err(json!({ "code": -32601, "message": "method not found" })),
+        });
+        let (url, received) = rpc_server(handler).await;
+        let client = testing::client(&url, 1);
+        let signer = client.signer(&format!("{:064x}", 1)).unwrap();
+        let (token, from, to) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+
+        let error = transfer::execute_transfer_from(&client, &signer, token, from, to, U256::from(101), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InsufficientAllowance { owner, spender, allowance, amount }
+                if owner == from && spender == signer.address() && allowance == U256::from(100) && amount == U256::from(101)
+        ));
+        assert_eq!(error.class(), ErrorClass::Fatal);
+        assert!(calls(&received, "eth_sendRawTransaction").is_empty());
+        assert!(calls(&received, "eth_estimateGas").is_empty());
+    }
+}
@@ -0,0 +1,405 @@
+use anyhow::{anyhow, bail, Context};
+use ethers::abi::{Event, Function, HumanReadableParser, ParamType, RawLog, Token};
+use ethers::prelude::*;
+use std::sync::Arc;
+
+use crate::blacklist_events::{BlacklistChange, BlacklistEvent};
+use crate::chains::ChainInfo;
+
+/// A token issuer's freeze list, as seen by the monitor.
+///
+/// An adapter knows how to ask the token whether an address is frozen and how to
+/// decode the logs emitted when the issuer freezes, unfreezes or burns the funds of
+/// an address.
+pub trait FreezeChecker: Send + Sync {
+    /// Short token name used in logs and alerts, e.g. `USDT`.
+    fn name(&self) -> &str;
+    /// Network the token contract lives on.
+    fn chain(&self) -> &ChainInfo;
+    fn token(&self) -> Address;
+    fn decimals(&self) -> u32;
+    /// First block worth scanning for freeze logs.
+    fn deployment_block(&self) -> u64;
+    /// Calldata of the freeze status check for `address`.
+    fn encode_check(&self, address: Address) -> Bytes;
+    /// Decodes the return data of the status check.
+    fn decode_check(&self, data: &[u8]) -> Option<bool>;
+    /// Log filter matching every freeze related event of the token.
+    fn event_filter(&self) -> Filter;
+    /// Decodes a raw log, returning `None` for unrelated logs.
+    fn decode_log(&self, log: &Log) -> Option<BlacklistEvent>;
+    /// Decodes a freeze or unfreeze call in a pending transaction, made to the token or
+    /// wrapped in a call to an owner contract, returning the address it would affect.
+    fn decode_call(&self, tx: &Transaction) -> Option<(Address, BlacklistChange)>;
+}
+
+// Human readable description of a freeze list, used for built-in and configured adapters
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckerSpec {
+    pub name: String,
+    pub token: Address,
+    pub decimals: u32,
+    pub deployment_block: u64,
+    // e.g. "isBlackListed(address)"; a `view returns (bool)` suffix is assumed when missing
+    pub check_function: String,
+    // e.g. "Blacklisted(address indexed _account)"
+    pub freeze_event: String,
+    pub unfreeze_event: String,
+    pub destroy_event: Option<String>,
+    // Owner functions that freeze and unfreeze an address, e.g. "addBlackList(address)"
+    pub freeze_function: Option<String>,
+    pub unfreeze_function: Option<String>,
+}
+
+/// Freeze list contracts of the built-in tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FreezeList {
+    // Tether USD on Ethereum Mainnet. Its blacklist events do not index the address.
+    Tether,
+    // TetherTokenV2, the USDT and USDT0 deployments on other chains
+    TetherV2,
+    // Circle's FiatToken, USDC on every chain it issues natively
+    FiatToken,
+}
+
+impl CheckerSpec {
+    /// Freeze list of a built-in token at `token`, with the deployment block of the
+    /// Ethereum Mainnet contract when there is one.
+    pub fn builtin(name: &str, list: FreezeList, token: Address) -> Self {
+        let (deployment_block, check, freeze, unfreeze, destroy, freeze_call, unfreeze_call) = match list {
+            FreezeList::Tether => (
+                4_634_748,
+                "isBlackListed(address)",
+                "AddedBlackList(address _user)",
+                "RemovedBlackList(address _user)",
+                Some("DestroyedBlackFunds(address _blackListedUser, uint256 _balance)"),
+                "addBlackList(address)",
+                "removeBlackList(address)",
+            ),
+            FreezeList::TetherV2 => (
+                0,
+                "isBlocked(address)",
+                "BlockPlaced(address indexed _user)",
+                "BlockReleased(address indexed _user)",
+                Some("DestroyedBlockedFunds(address indexed _blockedUser, uint256 _balance)"),
+                "addToBlockedList(address)",
+                "removeFromBlockedList(address)",
+            ),
+            FreezeList::FiatToken => (
+                6_082_465,
+                "isBlacklisted(address)",
+                "Blacklisted(address indexed _account)",
+                "UnBlacklisted(address indexed _account)",
+                None,
+                "blacklist(address)",
+                "unBlacklist(address)",
+            ),
+        };
+        CheckerSpec {
+            name: name.to_string(),
+            token,
+            decimals: 6,
+            deployment_block,
+            check_function: check.to_string(),
+            freeze_event: freeze.to_string(),
+            unfreeze_event: unfreeze.to_string(),
+            destroy_event: destroy.map(str::to_string),
+            freeze_function: Some(freeze_call.to_string()),
+            unfreeze_function: Some(unfreeze_call.to_string()),
+        }
+    }
+}
+
+/// Builds the freeze checkers of `chain` from validated specs.
+pub fn checkers(chain: &Arc<ChainInfo>, specs: &[CheckerSpec]) -> anyhow::Result<Vec<Arc<dyn FreezeChecker>>> {
+    let mut checkers: Vec<Arc<dyn FreezeChecker>> = Vec::new();
+    for spec in specs {
+        let checker = SignatureChecker::new(spec.clone(), chain.clone())
+            .with_context(|| format!("Invalid freeze checker {} on {}", spec.name, chain.name))?;
+        checkers.push(Arc::new(checker));
+    }
+    Ok(checkers)
+}
+
+/// Freeze checker driven entirely by function and event signatures.
+pub struct SignatureChecker {
+    spec: CheckerSpec,
+    chain: Arc<ChainInfo>,
+    check: Function,
+    freeze_event: Event,
+    unfreeze_event: Event,
+    destroy_event: Option<Event>,
+    freeze_call: Option<Function>,
+    unfreeze_call: Option<Function>,
+}
+
+impl SignatureChecker {
+    pub fn new(spec: CheckerSpec, chain: Arc<ChainInfo>) -> anyhow::Result<Self> {
+        let check = parse_function(&spec.check_function)?;
+        if check.inputs.len() != 1 {
+            bail!("{} must take a single address argument", spec.check_function);
+        }
+        let freeze_event = parse_event(&spec.freeze_event)?;
+        let unfreeze_event = parse_event(&spec.unfreeze_event)?;
+        let destroy_event = spec.destroy_event.as_deref().map(parse_event).transpose()?;
+        let freeze_call = spec.freeze_function.as_deref().map(parse_call).transpose()?;
+        let unfreeze_call = spec.unfreeze_function.as_deref().map(parse_call).transpose()?;
+        Ok(SignatureChecker {
+            spec,
+            chain,
+            check,
+            freeze_event,
+            unfreeze_event,
+            destroy_event,
+            freeze_call,
+            unfreeze_call,
+        })
+    }
+
+    fn calls(&self) -> impl Iterator<Item = ([u8; 4], BlacklistChange)> + '_ {
+        [(&self.freeze_call, BlacklistChange::Added), (&self.unfreeze_call, BlacklistChange::Removed)]
+            .into_iter()
+            .filter_map(|(call, change)| Some((call.as_ref()?.short_signature(), change)))
+    }
+
+    fn events(&self) -> impl Iterator<Item = &Event> {
+        [&self.freeze_event, &self.unfreeze_event]
+            .into_iter()
+            .chain(self.destroy_event.as_ref())
+    }
+}
+
+fn parse_function(signature: &str) -> anyhow::Result<Function> {
+    let signature = signature.trim();
+    let mut normalized = match signature.strip_prefix("function ") {
+        Some(rest) => format!("function {}", rest.trim()),
+        None => format!("function {}", signature),
+    };
+    if !normalized.contains("returns") {
+        normalized.push_str(" view returns (bool)");
+    }
+    HumanReadableParser::parse_function(&normalized)
+        .map_err(|e| anyhow!("Invalid function signature {}: {}", signature, e))
+}
+
+// Owner functions taking the affected address first, e.g. "addBlackList(address)"
+fn parse_call(signature: &str) -> anyhow::Result<Function> {
+    let signature = signature.trim();
+    let normalized = match signature.strip_prefix("function ") {
+        Some(rest) => format!("function {}", rest.trim()),
+        None => format!("function {}", signature),
+    };
+    let function = HumanReadableParser::parse_function(&normalized)
+        .map_err(|e| anyhow!("Invalid function signature {}: {}", signature, e))?;
+    if !matches!(function.inputs.first().map(|input| &input.kind), Some(ParamType::Address)) {
+        bail!("{} must take the affected address as its first argument", signature);
+    }
+    Ok(function)
+}
+
+// The address in an ABI word, if its upper 12 bytes are zero
+fn address_word(word: &[u8]) -> Option<Address> {
+    (word.len() >= 32 && word[..12].iter().all(|byte| *byte == 0)).then(|| Address::from_slice(&word[12..32]))
+}
+
+fn parse_event(signature: &str) -> anyhow::Result<Event> {
+    let signature = signature.trim();
+    let normalized = if signature.starts_with("event ") {
+        signature.to_string()
+    } else {
+        format!("event {}", signature)
+    };
+    HumanReadableParser::parse_event(&normalized)
+        .map_err(|e| anyhow!("Invalid event signature {}: {}", signature, e))
+}
+
+impl FreezeChecker for SignatureChecker {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn chain(&self) -> &ChainInfo {
+        &self.chain
+    }
+
+    fn token(&self) -> Address {
+        self.spec.token
+    }
+
+    fn decimals(&self) -> u32 {
+        self.spec.decimals
+    }
+
+    fn deployment_block(&self) -> u64 {
+        self.spec.deployment_block
+    }
+
+    fn encode_check(&self, address: Address) -> Bytes {
+        self.check
+            .encode_input(&[Token::Address(address)])
+            .expect("check function takes a single address")
+            .into()
+    }
+
+    fn decode_check(&self, data: &[u8]) -> Option<bool> {
+        match self.check.decode_output(data).ok()?.first()? {
+            Token::Bool(frozen) => Some(*frozen),
+            _ => None,
+        }
+    }
+
+    fn event_filter(&self) -> Filter {
+        let topics: Vec<H256> = self.events().map(|event| event.signature()).collect();
+        Filter::new().address(self.spec.token).topic0(topics)
+    }
+
+    fn decode_log(&self, log: &Log) -> Option<BlacklistEvent> {
+        if log.address != self.spec.token {
+            return None;
+        }
+        let topic0 = *log.topics.first()?;
+        let event = self.events().find(|event| event.signature() == topic0)?;
+        let parsed = event
+            .parse_log(RawLog {
+                topics: log.topics.clone(),
+                data: log.data.to_vec(),
+            })
+            .ok()?;
+
+        // The affected address is the first address parameter, indexed or not
+        let address = parsed.params.iter().find_map(|param| match param.value {
+            Token::Address(address) => Some(address),
+            _ => None,
+        })?;
+        let change = if event.signature() == self.freeze_event.signature() {
+            BlacklistChange::Added
+        } else if event.signature() == self.unfreeze_event.signature() {
+            BlacklistChange::Removed
+        } else {
+            let amount = parsed.params.iter().find_map(|param| match param.value {
+                Token::Uint(amount) => Some(amount),
+                _ => None,
+            })?;
+            BlacklistChange::Destroyed(amount)
+        };
+
+        Some(BlacklistEvent {
+            chain_id: self.chain.chain_id,
+            token: log.address,
+            address,
+            change,
+            block_number: log.block_number?.as_u64(),
+            block_hash: log.block_hash,
+            tx_hash: log.transaction_hash?,
+            log_index: log.log_index?,
+        })
+    }
+
+    fn decode_call(&self, tx: &Transaction) -> Option<(Address, BlacklistChange)> {
+        let input = tx.input.as_ref();
+        if tx.to == Some(self.spec.token) {
+            let (selector, change) = self.calls().find(|(selector, _)| input.starts_with(selector))?;
+            return Some((address_word(&input[selector.len()..])?, change));
+        }
+        // Owner contracts such as multisigs take the call as ABI encoded bytes next to the
+        // token address, so both sit on word boundaries after the outer selector
+        let words = input.get(4..)?;
+        let token_word = words.chunks(32).any(|word| address_word(word) == Some(self.spec.token));
+        if !token_word {
+            return None;
+        }
+        words.chunks(32).enumerate().find_map(|(index, word)| {
+            let (_, change) = self.calls().find(|(selector, _)| word.starts_with(selector))?;
+            Some((address_word(words.get(index * 32 + 4..)?)?, change))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{self, Token};
+    use ethers::utils::id;
+
+    use crate::chains;
+
+    const TOKEN: Address = Address::repeat_byte(0x70);
+    const TARGET: Address = Address::repeat_byte(0xaa);
+    const SAFE: Address = Address::repeat_byte(0x5a);
+
+    fn checker(list: FreezeList) -> SignatureChecker {
+        let chain = Arc::new(chains::builtin(1).unwrap());
+        SignatureChecker::new(CheckerSpec::builtin("USDT", list, TOKEN), chain).unwrap()
+    }
+
+    fn call(signature: &str, args: &[Token]) -> Vec<u8> {
+        [&id(signature)[..], &abi::encode(args)].concat()
+    }
+
+    fn tx(to: Address, input: Vec<u8>) -> Transaction {
+        Transaction { to: Some(to), input: input.into(), ..Default::default() }
+    }
+
+    // Gnosis Safe execTransaction(to, value, data, ...) carrying `data` for `to`
+    fn wrapped(to: Address, data: Vec<u8>) -> Transaction {
+        let input = call(
+            "execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)",
+            &[
+                Token::Address(to),
+                Token::Uint(U256::zero()),
+                Token::Bytes(data),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+                Token::Address(Address::zero()),
+                Token::Address(Address::zero()),
+                Token::Bytes(vec![0x11; 65]),
+            ],
+        );
+        tx(SAFE, input)
+    }
+
+    #[test]
+    fn decodes_direct_freeze_calls() {
+        let checker = checker(FreezeList::Tether);
+        let freeze = tx(TOKEN, call("addBlackList(address)", &[Token::Address(TARGET)]));
+        assert_eq!(checker.decode_call(&freeze), Some((TARGET, BlacklistChange::Added)));
+        let unfreeze = tx(TOKEN, call("removeBlackList(address)", &[Token::Address(TARGET)]));
+        assert_eq!(checker.decode_call(&unfreeze), Some((TARGET, BlacklistChange::Removed)));
+    }
+
+    #[test]
+    fn decodes_the_functions_of_each_freeze_list() {
+        let v2 = checker(FreezeList::TetherV2);
+        let freeze = tx(TOKEN, call("addToBlockedList(address)", &[Token::Address(TARGET)]));
+        assert_eq!(v2.decode_call(&freeze), Some((TARGET, BlacklistChange::Added)));
+        assert_eq!(checker(FreezeList::Tether).decode_call(&freeze), None);
+
+        let fiat = checker(FreezeList::FiatToken);
+        let unfreeze = tx(TOKEN, call("unBlacklist(address)", &[Token::Address(TARGET)]));
+        assert_eq!(fiat.decode_call(&unfreeze), Some((TARGET, BlacklistChange::Removed)));
+    }
+
+    #[test]
+    fn decodes_calls_wrapped_by_an_owner_contract() {
+        let checker = checker(FreezeList::Tether);
+        let inner = call("removeBlackList(address)", &[Token::Address(TARGET)]);
+        assert_eq!(checker.decode_call(&wrapped(TOKEN, inner.clone())), Some((TARGET, BlacklistChange::Removed)));
+
+        // The same call for another token
+        assert_eq!(checker.decode_call(&wrapped(Address::repeat_byte(0x71), inner)), None);
+    }
+
+    #[test]
+    fn ignores_other_calls() {
+        let checker = checker(FreezeList::Tether);
+        let transfer = call("transfer(address,uint256)", &[Token::Address(TARGET), Token::Uint(U256::one())]);
+        assert_eq!(checker.decode_call(&tx(TOKEN, transfer.clone())), None);
+        assert_eq!(checker.decode_call(&wrapped(TOKEN, transfer)), None);
+        assert_eq!(checker.decode_call(&tx(TOKEN, Vec::new())), None);
+        // Cut short before the address
+        let short = call("addBlackList(address)", &[Token::Address(TARGET)])[..20].to_vec();
+        assert_eq!(checker.decode_call(&tx(TOKEN, short)), None);
+    }
+}
@@ -1,262 +1,238 @@
 use ethers::prelude::*;               // Ethereum interaction via ethers-rs
 use std::sync::Arc;                   // Thread-safe reference counting
-use std::time::Duration;              // For delay between checks
+use std::time::Duration;
 use dotenv::dotenv;                   // Load environment variables from .env
-use std::env;                         // Access environment variables
-use chrono::Local;
+use clap::Parser;                     // Command line parsing
+
+use cli::{Command, ConfigCommand};
 
 macro_rules! println_time {
     ($($arg:tt)*) => {
-        println!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), format_args!($($arg)*))
+        println!("[{}] {}", ::chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), format_args!($($arg)*))
     };
 }
 
-// Define the USDT contract with only the isBlackListed function
-abigen!(
-    UsdtContract,
-    r#"[function isBlackListed(address) view returns (bool)]"#,
-);
-
-// USDT contract address on Ethereum Mainnet
-const USDT_CONTRACT_ADDRESS: &str = "0xdAC17F958D2ee523a2206206994597C13D831ec7";
+mod alerts;
+mod backfill;
+mod blacklist_events;
+mod chains;
+mod cli;
+mod client;
+mod config;
+mod contracts;
+mod error;
+mod freeze;
+mod mempool;
+mod monitor;
+mod multicall;
+mod relay;
+mod reload;
+mod rescue;
+mod retry;
+mod rpc;
+mod store;
+mod sweeper;
+mod telegram;
+#[cfg(test)]
+mod testing;
+mod transfer;
+mod watchlist;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Load .env configuration
     dotenv().ok();
-    // Load environment variables
-    let provider_url = env::var("ETH_RPC_URL")?;
-    let telegram_bot_token = env::var("TELEGRAM_BOT_TOKEN")?;
-    let telegram_chat_id = env::var("TELEGRAM_CHAT_ID")?;
-    let target_address = env::var("TARGET_ADDRESS")?;
-
-    // Connect to Ethereum provider (e.g., Infura)
-    let provider = Provider::<Http>::try_from(provider_url)?;
-    let client = Arc::new(provider);
-
-    // Instantiate the USDT contract
-    let usdt_address: Address = USDT_CONTRACT_ADDRESS.parse()?;
-    let contract = UsdtContract::new(usdt_address, client.clone());
-
-    // Parse the target Ethereum address to monitor
-    let target: Address = target_address.parse()?;
-
-    // Check initial blacklist status
-    let mut last_status = contract.is_black_listed(target).call().await?;
-    println_time!("Initial blacklist status: {}", last_status);
-
+    let cli = cli::Cli::parse();
+    // Configuration file (--config, CONFIG_FILE or config.toml) with environment overrides
+    let config_file = config::config_path(cli.config.as_deref());
+    let command = cli.command.unwrap_or(Command::Monitor { dry_run: false });
+    if let Command::Config { command: ConfigCommand::Check } = command {
+        return config::check(config_file.as_deref()).await;
+    }
+    let config = config::load(config_file.as_deref())?;
+    for warning in &config.warnings {
+        println_time!("Warning: {}", warning);
+    }
+    let store = Arc::new(store::Store::open(&config.database_path)?);
+
+    // Networks to watch (Ethereum Mainnet by default), each with a pool of RPC endpoints
+    // and token freeze lists queried through Multicall3
+    let mut networks = Vec::new();
+    for chain in &config.chains {
+        let client = client::ChainClient::connect(chain.chain.clone(), config.rpc, config.relay)?;
+        let checkers = freeze::checkers(&chain.chain, &chain.issuers)?;
+        networks.push((client, checkers));
+    }
+    // Client of the chain given by --chain, or of the one selected by chain_id (CHAIN_ID,
+    // the first configured chain by default)
+    let chain_network = |chain: &cli::ChainArgs| {
+        let chain_id = match chain.chain {
+            Some(chain_id) => chain_id,
+            None => {
+                let chain = config.transaction_chain().ok_or_else(|| anyhow::anyhow!("No chain selected for transactions"))?;
+                chain.chain.chain_id
+            }
+        };
+        networks
+            .iter()
+            .find(|(client, _)| client.chain.chain_id == chain_id)
+            .ok_or_else(|| anyhow::anyhow!("Chain {} is not configured", chain_id))
+    };
+    let chain_client = |chain: &cli::ChainArgs| chain_network(chain).map(|(client, _)| client);
+    // Sweeper of PRIVATE_KEY, with the command line taking precedence over the configuration
+    let sweeper = |sweep: cli::SweepArgs, tx: cli::TxArgs, interval: Option<u64>, notify: bool| {
+        let (client, checkers) = chain_network(&tx.chain)?;
+        let options = sweeper::SweeperOptions {
+            recipient: sweep
+                .recipient
+                .or(config.sweep.recipient)
+                .ok_or_else(|| anyhow::anyhow!("Missing sweep.recipient (RECIPIENT_ADDRESS)"))?,
+            min_balance: sweep.min_balance.unwrap_or(config.sweep.min_balance),
+            reserve: sweep.reserve.unwrap_or(config.sweep.reserve),
+            interval: interval.map(Duration::from_secs).unwrap_or(config.sweep.interval),
+            retry: config.retry,
+            degraded_after: config.monitor.degraded_after,
+            dry_run: tx.dry_run,
+        };
+        let tokens = if sweep.tokens.is_empty() { &config.sweep.tokens } else { &sweep.tokens };
+        let tokens = sweeper::SweepToken::resolve(client, checkers, tokens)?;
+        let signer = client.signer_from_env(&["PRIVATE_KEY"])?;
+        let telegram = config
+            .telegram
+            .as_ref()
+            .filter(|_| notify)
+            .map(|telegram| telegram::TelegramBot::new(telegram.bot_token.clone(), telegram.chat_id.clone()));
+        anyhow::Ok(sweeper::Sweeper::new(client, signer, options, tokens, telegram))
+    };
 
-    // Periodically check the blacklist status
-    loop {
-        let current_status = contract.is_black_listed(target).call().await?;
-        if current_status {
-            println_time!("Address {} remains on USDT's blacklist.", target);
+    let dry_run = match command {
+        Command::Monitor { dry_run } => dry_run,
+        Command::Config { .. } => unreachable!("handled before loading the configuration"),
+        // Subcommands working on the local blacklist history
+        Command::Backfill => {
+            for (client, checkers) in &networks {
+                for checker in checkers {
+                    backfill::run(client.provider(), checker.as_ref(), &store, config.retry).await?;
+                }
+            }
+            return Ok(());
+        }
+        Command::Timeline { address } => {
+            let checkers: Vec<_> = networks.iter().flat_map(|(_, checkers)| checkers.clone()).collect();
+            return backfill::print_timeline(&store, &checkers, address);
+        }
+        // Current freeze status of one address on every configured chain and token
+        Command::CheckBlacklist { address, chain, token } => {
+            for (client, checkers) in &networks {
+                if chain.is_some_and(|chain_id| chain_id != client.chain.chain_id) {
+                    continue;
+                }
+                for checker in checkers {
+                    if token.as_ref().is_some_and(|token| !token.eq_ignore_ascii_case(checker.name())) {
+                        continue;
+                    }
+                    let statuses =
+                        multicall::batch_is_frozen(client.provider(), checker.as_ref(), &[address], BlockNumber::Latest.into()).await?;
+                    match statuses.get(&address).copied().flatten() {
+                        Some(frozen) => println!("{} {}: {}", client.chain.name, checker.name(), if frozen { "blacklisted" } else { "not blacklisted" }),
+                        None => println!("{} {}: lookup failed", client.chain.name, checker.name()),
+                    }
+                }
+            }
+            return Ok(());
+        }
+        Command::Allowance { token, owner, spender, chain } => {
+            let client = chain_client(&chain)?;
+            let token = client.token(&token)?;
+            let allowance = client.erc20(token).allowance(owner, spender).call().await.map_err(error::Error::from)?;
+            println!("{} of token {:?} on {}", allowance, token, client.chain.name);
+            return Ok(());
         }
-        if !current_status {
-            println_time!("Address {} has been removed from USDT blacklist", target);
-            let msg = format!("🚨 Address {} has been unblacklisted by USDT contract!", target);
-            send_telegram_message(&telegram_bot_token, &telegram_chat_id, &msg).await?;
+        // Transactions signed with local keys; amounts are in the token's smallest unit
+        // A single sweep is only logged, the service also notifies Telegram
+        Command::Sweep { sweep, tx } => {
+            sweeper(sweep, tx, None, false)?.run_once().await?;
+            return Ok(());
         }
-        // If address was blacklisted before and now it's not, send Telegram notification
-        if last_status && !current_status {
-            println_time!("Address {} has been removed from USDT blacklist", target);
-            let msg = format!("🚨 Address {} has been unblacklisted by USDT contract!", target);
-            send_telegram_message(&telegram_bot_token, &telegram_chat_id, &msg).await?;
+        Command::Sweeper { sweep, interval, tx } => {
+            return sweeper(sweep, tx, interval, true)?.run().await;
         }
-        // Update status for next loop iteration
-        last_status = current_status;
+        Command::Transfer { token, to, amount, tx } => {
+            let client = chain_client(&tx.chain)?;
+            let token = client.token(&token)?;
+            let signer = client.signer_from_env(&["PRIVATE_KEY"])?;
+            transfer::transfer_token(client, &signer, token, to, amount, tx.dry_run).await?;
+            return Ok(());
+        }
+        Command::Approve { token, spender, amount, tx } => {
+            let client = chain_client(&tx.chain)?;
+            let token = client.token(&token)?;
+            let signer = client.signer_from_env(&["PRIVATE_KEY_OWNER", "PRIVATE_KEY"])?;
+            transfer::approve_spender(client, &signer, token, spender, amount, tx.dry_run).await?;
+            return Ok(());
+        }
+        Command::TransferFrom { token, from, to, amount, tx } => {
+            let client = chain_client(&tx.chain)?;
+            let token = client.token(&token)?;
+            let signer = client.signer_from_env(&["PRIVATE_KEY_SPENDER"])?;
+            transfer::execute_transfer_from(client, &signer, token, from, to, amount, tx.dry_run).await?;
+            return Ok(());
+        }
+    };
 
-        // Wait 30 seconds before checking again
-        tokio::time::sleep(Duration::from_secs(60)).await;
+    let problems = config.monitor_problems();
+    if !problems.is_empty() {
+        anyhow::bail!("Cannot start the monitor: {}", problems.join("; "));
     }
-}
-
-// Function to send a message via Telegram Bot
-async fn send_telegram_message(token: &str, chat_id: &str, text: &str) -> Result<(), reqwest::Error> {
-    let url = format!(
-        "https://api.telegram.org/bot{}/sendMessage",
-        token
-    );
-
-    let client = reqwest::Client::new();
-    let res = client
-        .post(&url)
-        .json(&serde_json::json!({
-            "chat_id": chat_id,
-            "text": text
-        }))
-        .send()
-        .await?;
-
-    // Print result
-    if res.status().is_success() {
-        println_time!("✅ Telegram message sent.");
-    } else {
-        eprintln!("❌ Failed to send Telegram message: {:?}", res.text().await);
+    println_time!("Loaded {} watched address(es)", config.watchlist.len());
+    let mode = config.monitor.mode;
+    let mempool = config.monitor.mempool;
+    // Watchlist, intervals and notifier settings are reloaded when the configuration or
+    // watchlist file changes, or on SIGHUP
+    let updates = reload::spawn(Arc::new(config))?;
+
+    // One monitor per chain and token, all sharing the database and configuration
+    let mut tasks = tokio::task::JoinSet::new();
+    for (client, checkers) in networks {
+        let chain = client.chain.clone();
+        // Keys of the watched addresses whose funds are moved out once they are cleared
+        let rescue = updates.borrow().rescue.clone();
+        let rescue_signers = if rescue.enabled { client.signers_from_env(config::RESCUE_KEYS_VAR)? } else { Vec::new() };
+        if !rescue_signers.is_empty() {
+            println_time!(
+                "Rescue enabled on {} for {} address(es){}",
+                chain.name,
+                rescue_signers.len(),
+                if dry_run { " (dry run)" } else { "" }
+            );
+        }
+        // One subscription per chain serves every freeze list on it
+        match (&chain.ws_url, mempool) {
+            (Some(ws_url), true) => {
+                let mut watcher = mempool::MempoolWatcher::new(ws_url.clone(), chain.clone(), checkers.clone(), updates.clone());
+                tasks.spawn(async move { watcher.run().await });
+            }
+            (None, true) => println_time!("Not watching the mempool of {}: it has no ws_url", chain.name),
+            (_, false) => {}
+        }
+        for checker in checkers {
+            println_time!("Watching the {} freeze list on {} at {:?}", checker.name(), chain.name, checker.token());
+            let rescuer = (!rescue_signers.is_empty())
+                .then(|| rescue::Rescuer::new(client.clone(), checker.clone(), &rescue_signers, rescue.clone(), dry_run));
+            let mut monitor = monitor::Monitor::new(client.provider(), checker, store.clone(), updates.clone(), rescuer);
+            // Optional WebSocket endpoint used for subscriptions in events mode
+            let ws_url = chain.ws_url.clone();
+            tasks.spawn(async move {
+                match (mode, ws_url) {
+                    (config::MonitorMode::Poll, _) => monitor.run_polling().await,
+                    (_, Some(ws_url)) => monitor.run_websocket(&ws_url).await,
+                    (_, None) => monitor.run_events().await,
+                }
+            });
+        }
+    }
+    while let Some(result) = tasks.join_next().await {
+        result??;
     }
-
     Ok(())
 }
-
-// mod usdt_blacklist_checker;
-// mod usdt_transfer;
-// mod telegram;
-// use std::sync::Arc;
-// use std::time::Duration;
-// use anyhow::{Context, Result};
-// use chrono::Local;
-// use ethers::{
-//     prelude::*,
-//     types::{Address, TransactionRequest},
-//     utils::format_ether,
-// };
-// use std::env;
-// use std::str::FromStr;
-// use teloxide::prelude::*;
-// use tokio::time;
-// use telegram::TelegramBot;
-//
-// use reqwest::Client;
-// use serde::Serialize;
-// use std::error::Error;
-// #[derive(Debug)]
-// struct Config {
-//     to_address: String,
-//     contract_address: String,
-//     bot_token: String,
-//     chat_id: String,
-//     rpc_url: String,
-//     sender_private_key: String,
-//     sender_address: Address,
-//     checker_address: String,
-//     recipient_address: Address,
-//     check_interval_minutes: u64,
-//     min_balance_to_transfer: f64,
-// }
-//
-// #[derive(Serialize)]
-// struct TelegramMessage {
-//     chat_id: String,
-//     text: String,
-//     #[serde(skip_serializing_if = "Option::is_none")]
-//     parse_mode: Option<String>,
-// }
-//
-//
-// macro_rules! send_telegram {
-//     ($bot_token:expr, $chat_id:expr, $text:expr) => {
-//         send_telegram_message($bot_token, $chat_id, $text, None).await
-//     };
-//     ($bot_token:expr, $chat_id:expr, $text:expr, $parse_mode:expr) => {
-//         send_telegram_message($bot_token, $chat_id, $text, Some($parse_mode.to_string())).await
-//     };
-// }
-//
-// async fn send_telegram_message(
-//     bot_token: &str,
-//     chat_id: &str,
-//     text: &str,
-//     parse_mode: Option<String>,
-// ) -> Result<(), Box<dyn Error>> {
-//     let client = Client::new();
-//     let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
-//
-//     let message = TelegramMessage {
-//         chat_id: chat_id.to_string(),
-//         text: text.to_string(),
-//         parse_mode,
-//     };
-//
-//     let response = client
-//         .post(&url)
-//         .json(&message)
-//         .send()
-//         .await?;
-//
-//     if !response.status().is_success() {
-//         let error_text = response.text().await?;
-//         return Err(format!("Telegram API error: {}", error_text).into());
-//     }
-//
-//     Ok(())
-// }
-//
-// #[tokio::main]
-// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-//     dotenv::dotenv().ok();
-//     let config = Config {
-//         to_address: std::env::var("TO_ADDRESS").context("Missing TO_ADDRESS")?,
-//         contract_address: std::env::var("CONTRACT_ADDRESS").context("Missing CONTRACT_ADDRESS")?,
-//         bot_token: std::env::var("BOT_TOKEN").context("Missing BOT_TOKEN")?,
-//         chat_id: std::env::var("CHAT_ID").context("Missing CHAT_ID")?,
-//         rpc_url: std::env::var("RPC_URL").context("Missing RPC_URL")?,
-//         sender_private_key: std::env::var("PRIVATE_KEY").context("Missing PRIVATE_KEY")?,
-//         checker_address: std::env::var("CHECKER_ADDRESS").context("Missing CHECKER_ADDRESS")?,
-//         sender_address: std::env::var("SENDER_ADDRESS")
-//             .context("Missing SENDER_ADDRESS")?
-//             .parse()?,
-//         recipient_address: std::env::var("RECIPIENT_ADDRESS")
-//             .context("Missing RECIPIENT_ADDRESS")?
-//             .parse()?,
-//         check_interval_minutes: std::env::var("CHECK_INTERVAL_MINUTES")
-//             .unwrap_or("1".to_string())
-//             .parse()?,
-//         min_balance_to_transfer: std::env::var("MIN_BALANCE_TO_TRANSFER")
-//             .unwrap_or("0.01".to_string())
-//             .parse()?,
-//     };
-//     let transfer_amount: u64 = env::var("TRANSFER_AMOUNT")
-//         .unwrap_or_else(|_| panic!("TRANSFER_AMOUNT not found in .env"))
-//         .parse()
-//         .unwrap_or_else(|_| panic!("Failed to parse TRANSFER_AMOUNT as u64"));
-//     let tx_hash = usdt_transfer::transfer_usdt(
-//         &config.rpc_url,
-//         &config.sender_private_key,
-//         &config.contract_address,
-//         &config.to_address,
-//         transfer_amount,
-//     )
-//         .await?;
-//
-//     let provider =
-//         Provider::<Http>::try_from(&config.rpc_url)?.interval(Duration::from_millis(500));
-//     let provider = Arc::new(provider);
-//     let wallet = config
-//         .sender_private_key
-//         .parse::<LocalWallet>()?
-//         .with_chain_id(1u64); //  chain_id = 1
-//                               // .with_chain_id(11155111u64); //  chain_id = 1
-//     let client = SignerMiddleware::new(provider.clone(), wallet);
-//     let mut interval = time::interval(Duration::from_secs(5));
-//     // let bot = TelegramBot::new(config.bot_token.to_string(), config.chat_id.to_string());
-//     loop {
-//         interval.tick().await;
-//
-//         // Address to check
-//         let address_to_check = &config.checker_address;
-//
-//         // Check blacklist status
-//         match usdt_blacklist_checker::check_usdt_blacklist(address_to_check, &config.rpc_url).await
-//         {
-//             Ok(is_blacklisted) => {
-//                 if is_blacklisted {
-//                     println!(
-//                         "ADDRESS {} IS {} BLACKLISTED BY Tether",
-//                         address_to_check,
-//                         if is_blacklisted { "" } else { "not " }
-//                     )
-//                 }else{
-//                     send_telegram!(&config.bot_token, &config.chat_id, "Hello from Rust macro!");
-//                     // bot.send_message("ADDRESS IS UNLOCKED ,PLEASE CHECK").await?;
-//                 }
-//             }
-//             Err(e) => eprintln!("Error checking blacklist status: {:?}", e),
-//         }
-//         if let Err(e) = usdt_transfer::check_and_transfer(
-//             &client,
-//             &config
-//         ).await {
-//             eprintln!("[{}] Error: {}", Local::now(), e);
-//         }
-//     }
-// }
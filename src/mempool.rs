@@ -0,0 +1,131 @@
+use anyhow::Context;
+use ethers::prelude::*;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use crate::alerts::{AlertContext, AlertKind, BlacklistState};
+use crate::blacklist_events::BlacklistChange;
+use crate::chains::ChainInfo;
+use crate::config::{Config, TelegramSettings};
+use crate::freeze::FreezeChecker;
+use crate::retry::Reconnect;
+use crate::telegram::TelegramBot;
+
+// Pending transactions remembered so a rebroadcast is not alerted twice
+const MAX_SEEN: usize = 10_000;
+
+/// Watches the mempool of one chain for freeze and unfreeze calls that touch watched
+/// addresses, and alerts before they are mined. The monitors still report the change
+/// once it is confirmed.
+pub struct MempoolWatcher {
+    ws_url: String,
+    chain: Arc<ChainInfo>,
+    checkers: Vec<Arc<dyn FreezeChecker>>,
+    config: watch::Receiver<Arc<Config>>,
+    seen: HashSet<TxHash>,
+    // Kept until the settings it was built from change
+    telegram: Option<(TelegramSettings, TelegramBot)>,
+}
+
+impl MempoolWatcher {
+    pub fn new(
+        ws_url: String,
+        chain: Arc<ChainInfo>,
+        checkers: Vec<Arc<dyn FreezeChecker>>,
+        config: watch::Receiver<Arc<Config>>,
+    ) -> Self {
+        MempoolWatcher { ws_url, chain, checkers, config, seen: HashSet::new(), telegram: None }
+    }
+
+    /// Follows the pending transactions, reconnecting with exponential backoff.
+    pub async fn run(&mut self) -> anyhow::Result<()> {
+        let mut reconnect = Reconnect::default();
+        loop {
+            match self.follow(&mut reconnect).await {
+                Ok(()) => println_time!("Pending transaction subscription on {} closed", self.chain.name),
+                Err(e) => println_time!("Pending transaction subscription on {} failed: {:#}", self.chain.name, e),
+            }
+            reconnect.wait().await;
+        }
+    }
+
+    async fn follow(&mut self, reconnect: &mut Reconnect) -> anyhow::Result<()> {
+        let ws = Provider::<Ws>::connect(&self.ws_url)
+            .await
+            .context("Failed to connect to WebSocket provider")?;
+        let mut pending = ws
+            .subscribe_full_pending_txs()
+            .await
+            .context("Failed to subscribe to pending transactions")?;
+        println_time!("Watching pending freeze calls on {} over WebSocket", self.chain.name);
+        reconnect.connected();
+        while let Some(tx) = pending.next().await {
+            self.inspect(&ws, &tx).await;
+        }
+        Ok(())
+    }
+
+    async fn inspect(&mut self, ws: &Provider<Ws>, tx: &Transaction) {
+        let Some((checker, address, change)) = self
+            .checkers
+            .iter()
+            .find_map(|checker| checker.decode_call(tx).map(|(address, change)| (checker, address, change)))
+        else {
+            return;
+        };
+        let config = self.config.borrow().clone();
+        let Some(entry) = config.watchlist.iter().find(|entry| entry.address == address) else {
+            return;
+        };
+        let (kind, wanted) = match change {
+            BlacklistChange::Added => (AlertKind::PendingAdded, entry.alerts.on_add),
+            BlacklistChange::Removed => (AlertKind::PendingRemoved, entry.alerts.on_remove),
+            BlacklistChange::Destroyed(_) => return,
+        };
+        if !wanted {
+            return;
+        }
+        if self.seen.len() >= MAX_SEEN {
+            self.seen.clear();
+        }
+        if !self.seen.insert(tx.hash) {
+            return;
+        }
+        // Only for the message, so a failed lookup does not hold the alert back
+        let block = ws.get_block_number().await.map(|block| block.as_u64()).unwrap_or_default();
+        let message = config.templates.render(
+            kind,
+            &AlertContext {
+                chain: &self.chain.name,
+                token: checker.name(),
+                decimals: checker.decimals(),
+                label: &entry.label,
+                address,
+                block,
+                amount: None,
+                state: BlacklistState::Unknown,
+                tx: Some(tx.hash),
+            },
+        );
+        println_time!("{}", message);
+        let Some(settings) = &config.telegram else { return };
+        let (telegram, retry) = (self.telegram(settings), config.retry);
+        tokio::spawn(async move {
+            if let Err(e) = retry.run("Sending a Telegram message", || async { Ok(telegram.send_message(&message).await?) }).await {
+                println_time!("Failed to send the pending transaction alert: {:#}", e);
+            }
+        });
+    }
+
+    fn telegram(&mut self, settings: &TelegramSettings) -> TelegramBot {
+        match &self.telegram {
+            Some((current, telegram)) if current == settings => telegram.clone(),
+            _ => {
+                let telegram = TelegramBot::new(settings.bot_token.clone(), settings.chat_id.clone());
+                self.telegram = Some((settings.clone(), telegram.clone()));
+                telegram
+            }
+        }
+    }
+}
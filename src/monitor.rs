@@ -0,0 +1,1013 @@
+use anyhow::{anyhow, Context};
+use ethers::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+use crate::alerts::{Alert, AlertContext, AlertKind, AlertTemplates, AlertTracker, BlacklistState, HealthContext};
+use crate::blacklist_events::{self, BlacklistChange, BlacklistEvent};
+use crate::config::{Config, MonitorSettings};
+use crate::error::Error;
+use crate::freeze::FreezeChecker;
+use crate::multicall;
+use crate::rescue::Rescuer;
+use crate::retry::{self, Health, Reconnect, RetryPolicy};
+use crate::store::Store;
+use crate::telegram::TelegramBot;
+use crate::watchlist::{AlertDirections, WatchEntry};
+
+
+// An alert Telegram did not accept, sent again at the end of the next iteration
+struct PendingAlert {
+    address: Address,
+    kind: AlertKind,
+    block: u64,
+    // State of the address the alert reports
+    state: BlacklistState,
+    message: String,
+}
+
+// Freeze statuses of the watched addresses read at one block
+struct Statuses {
+    block: u64,
+    hash: H256,
+    statuses: HashMap<Address, Option<bool>>,
+}
+
+// A freeze list change seen in a block that is not final yet, kept to notice a reorg
+#[derive(Debug, Clone)]
+struct Unconfirmed {
+    address: Address,
+    change: BlacklistChange,
+    block: u64,
+    hash: H256,
+    // Transaction that logged the change, when it came from a log
+    tx: Option<TxHash>,
+    alerted: bool,
+}
+
+// Freeze list monitor of one token on one chain for a set of watched addresses
+pub struct Monitor<M> {
+    client: Arc<M>,
+    checker: Arc<dyn FreezeChecker>,
+    watchlist: Vec<WatchEntry>,
+    alerts: AlertTracker,
+    templates: AlertTemplates,
+    // Position (block, log index) of the last applied event, so resyncs never apply a log twice
+    last_event: Option<(u64, U256)>,
+    // Blacklist history plus the monitor state that survives restarts
+    store: Arc<Store>,
+    telegram: TelegramBot,
+    pending: Vec<PendingAlert>,
+    unconfirmed: Vec<Unconfirmed>,
+    retry: RetryPolicy,
+    settings: MonitorSettings,
+    // Configurations published by the reload task, applied between iterations
+    config: watch::Receiver<Arc<Config>>,
+    // Set when addresses were added to the watchlist and still need a status check
+    reconcile_due: bool,
+    // Moves the funds of cleared addresses out when rescue is enabled
+    rescuer: Option<Rescuer>,
+    health: Health,
+}
+
+fn telegram_bot(config: &Config) -> TelegramBot {
+    let telegram = config.telegram.as_ref().expect("checked by monitor_problems");
+    TelegramBot::new(telegram.bot_token.clone(), telegram.chat_id.clone())
+}
+
+impl<M: Middleware + 'static> Monitor<M> {
+    pub fn new(
+        client: Arc<M>,
+        checker: Arc<dyn FreezeChecker>,
+        store: Arc<Store>,
+        mut config: watch::Receiver<Arc<Config>>,
+        rescuer: Option<Rescuer>,
+    ) -> Self {
+        let current = config.borrow_and_update().clone();
+        Monitor {
+            client,
+            checker,
+            watchlist: current.watchlist.clone(),
+            alerts: AlertTracker::new(current.monitor.renotify_interval),
+            templates: current.templates.clone(),
+            last_event: None,
+            store,
+            telegram: telegram_bot(&current),
+            pending: Vec::new(),
+            unconfirmed: Vec::new(),
+            retry: current.retry,
+            settings: current.monitor,
+            config,
+            reconcile_due: false,
+            rescuer,
+            health: Health::default(),
+        }
+    }
+
+    // Apply the latest configuration published since the last call. The alert state of
+    // addresses that stay watched is kept; added ones start from their persisted status.
+    fn reload(&mut self) {
+        if !self.config.has_changed().unwrap_or(false) {
+            return;
+        }
+        let config = self.config.borrow_and_update().clone();
+        self.templates = config.templates.clone();
+        self.telegram = telegram_bot(&config);
+        self.retry = config.retry;
+        self.settings = config.monitor;
+        self.alerts.set_renotify_interval(config.monitor.renotify_interval);
+        if let Some(rescuer) = &mut self.rescuer {
+            rescuer.set_settings(config.rescue.clone());
+        }
+
+        let watched = self.watched();
+        let added: Vec<Address> = config
+            .watchlist
+            .iter()
+            .map(|entry| entry.address)
+            .filter(|address| !watched.contains(address))
+            .collect();
+        self.watchlist = config.watchlist.clone();
+        if added.is_empty() {
+            return;
+        }
+        match self.store.load_statuses(self.chain_id(), self.checker.token()) {
+            Ok(persisted) => {
+                for address in &added {
+                    if let Some(status) = persisted.get(address) {
+                        self.alerts.assume(*address, BlacklistState::from_status(*status));
+                    }
+                }
+            }
+            Err(e) => println_time!("Failed to load the persisted status of newly watched addresses: {:#}", e),
+        }
+        self.reconcile_due = true;
+    }
+
+    // Sleep for `duration`, waking up early when a new configuration is published
+    async fn pause(&self, duration: Duration) {
+        let mut updates = self.config.clone();
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => {}
+            Ok(()) = updates.changed() => {}
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.checker.chain().chain_id
+    }
+
+    fn addresses(&self) -> Vec<Address> {
+        self.watchlist.iter().map(|entry| entry.address).collect()
+    }
+
+    fn label(&self, address: Address) -> String {
+        self.watchlist
+            .iter()
+            .find(|entry| entry.address == address)
+            .map(|entry| entry.label.clone())
+            .unwrap_or_else(|| format!("{:?}", address))
+    }
+
+    fn directions(&self, address: Address) -> Option<AlertDirections> {
+        self.watchlist
+            .iter()
+            .find(|entry| entry.address == address)
+            .map(|entry| entry.alerts)
+    }
+
+    async fn head(&self) -> anyhow::Result<u64> {
+        self.retry
+            .run("Fetching the block number", move || async move {
+                let block = self
+                    .client
+                    .get_block_number()
+                    .await
+                    .context("Failed to fetch block number")?;
+                Ok(block.as_u64())
+            })
+            .await
+    }
+
+    // Hash of block `block` on the chain the node currently follows
+    async fn block_hash(&self, block: u64) -> anyhow::Result<Option<H256>> {
+        self.retry
+            .run("Fetching the block hash", move || async move {
+                let block = self.client.get_block(block).await.context("Failed to fetch block")?;
+                Ok(block.and_then(|block| block.hash))
+            })
+            .await
+    }
+
+    // Fetch all statuses at the same block with a single aggregate3 call
+    async fn fetch_statuses(&self, block: u64) -> anyhow::Result<Statuses> {
+        self.fetch_statuses_of(&self.addresses(), block).await
+    }
+
+    // Statuses are read by block hash, so they are known to come from that exact block. The
+    // HTTP endpoints may not have seen `block` yet when it is a WebSocket head; their own
+    // latest block is read then, and returned in its place.
+    async fn fetch_statuses_of(&self, addresses: &[Address], block: u64) -> anyhow::Result<Statuses> {
+        let (block, hash) = match self.block_hash(block).await? {
+            Some(hash) => (block, hash),
+            None => {
+                let (latest, hash) = self.latest_block().await?;
+                println_time!("Block {} is not known to the RPC endpoints yet, reading block {} instead", block, latest);
+                (latest, hash)
+            }
+        };
+        let statuses = self
+            .retry
+            .run("Checking the freeze list", move || async move {
+                multicall::batch_is_frozen(self.client.clone(), self.checker.as_ref(), addresses, BlockId::Hash(hash)).await
+            })
+            .await?;
+        Ok(Statuses { block, hash, statuses })
+    }
+
+    async fn latest_block(&self) -> anyhow::Result<(u64, H256)> {
+        self.retry
+            .run("Fetching the latest block", move || async move {
+                let block = self.client.get_block(BlockNumber::Latest).await.context("Failed to fetch block")?;
+                let latest = block.and_then(|block| Some((block.number?.as_u64(), block.hash?)));
+                Ok(latest.ok_or_else(|| Error::BlockNotFound("latest".to_string()))?)
+            })
+            .await
+    }
+
+    // Persist an observed status and send whatever alert the state machine asks for. A
+    // transition seen in a block of known `hash` is tracked until that block is final.
+    async fn observe(
+        &mut self,
+        address: Address,
+        blacklisted: bool,
+        block: u64,
+        hash: Option<H256>,
+        tx: Option<TxHash>,
+    ) -> anyhow::Result<()> {
+        self.store.save_status(self.chain_id(), self.checker.token(), address, blacklisted, block)?;
+        let directions = self.directions(address);
+        match self.alerts.observe(address, blacklisted) {
+            Some(Alert::Initial(state)) => {
+                println_time!("Initial blacklist status of {} ({:?}) at block {}: {:?}", self.label(address), address, block, state);
+            }
+            Some(Alert::Transition { from, to }) => {
+                println_time!("Address {} ({:?}) moved from {:?} to {:?} at block {}", self.label(address), address, from, to, block);
+                let (change, kind, alerted) = match to {
+                    BlacklistState::Blacklisted => (BlacklistChange::Added, AlertKind::Added, directions.is_some_and(|d| d.on_add)),
+                    BlacklistState::Cleared => {
+                        // Before the alert, which may wait on Telegram
+                        let label = self.label(address);
+                        if let Some(rescuer) = &mut self.rescuer {
+                            rescuer.start(address, label, self.telegram.clone(), self.retry);
+                        }
+                        (BlacklistChange::Removed, AlertKind::Removed, directions.is_some_and(|d| d.on_remove))
+                    }
+                    BlacklistState::Unknown => return Ok(()),
+                };
+                if alerted {
+                    self.notify(address, kind, block, None).await?;
+                }
+                if let Some(hash) = hash {
+                    self.unconfirmed.push(Unconfirmed { address, change, block, hash, tx, alerted });
+                }
+            }
+            Some(Alert::Reminder(state)) => {
+                let wanted = directions.is_some_and(|d| match state {
+                    BlacklistState::Blacklisted => d.on_add,
+                    BlacklistState::Cleared => d.on_remove,
+                    BlacklistState::Unknown => false,
+                });
+                if wanted {
+                    self.notify(address, AlertKind::Reminder, block, None).await?;
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    // Each alert kind is sent at most once per block, even across restarts
+    async fn notify(&mut self, address: Address, kind: AlertKind, block: u64, amount: Option<U256>) -> anyhow::Result<()> {
+        let state = self.alerts.state(address);
+        if self.store.alert_sent(self.chain_id(), self.checker.token(), address, kind.as_str(), block)? {
+            // Delivered by an earlier run
+            self.start_reminders(address, kind, state);
+            return Ok(());
+        }
+        let label = self.label(address);
+        let msg = self.templates.render(
+            kind,
+            &AlertContext {
+                chain: &self.checker.chain().name,
+                token: self.checker.name(),
+                decimals: self.checker.decimals(),
+                label: &label,
+                address,
+                block,
+                amount,
+                state,
+                tx: None,
+            },
+        );
+        self.deliver(PendingAlert { address, kind, block, state, message: msg }).await
+    }
+
+    // Reminders of a state only follow a delivered alert about that same state
+    fn start_reminders(&mut self, address: Address, kind: AlertKind, state: BlacklistState) {
+        let about_state = matches!(kind, AlertKind::Added | AlertKind::Removed | AlertKind::Reminder);
+        if about_state && self.alerts.state(address) == state {
+            self.alerts.mark_notified(address);
+        }
+    }
+
+    fn delivered(&mut self, alert: &PendingAlert) -> anyhow::Result<()> {
+        self.store.record_alert(self.chain_id(), self.checker.token(), alert.address, alert.kind.as_str(), alert.block)?;
+        self.start_reminders(alert.address, alert.kind, alert.state);
+        Ok(())
+    }
+
+    async fn send(&self, text: &str) -> anyhow::Result<()> {
+        self.retry
+            .run("Sending a Telegram message", move || async move { Ok(self.telegram.send_message(text).await?) })
+            .await
+    }
+
+    // Send an alert, keeping it for the next iteration when Telegram stays unreachable
+    async fn deliver(&mut self, alert: PendingAlert) -> anyhow::Result<()> {
+        match self.send(&alert.message).await {
+            Ok(()) => self.delivered(&alert),
+            Err(e) => {
+                println_time!("Failed to send the {} alert for {:?}, keeping it for later: {:#}", alert.kind.as_str(), alert.address, e);
+                self.pending.push(alert);
+                Ok(())
+            }
+        }
+    }
+
+    // Re-send the alerts Telegram did not accept earlier, oldest first
+    async fn flush_pending(&mut self) -> anyhow::Result<()> {
+        while let Some(alert) = self.pending.first() {
+            self.send(&alert.message).await?;
+            let alert = self.pending.remove(0);
+            self.delivered(&alert)?;
+        }
+        Ok(())
+    }
+
+    // Record the outcome of a loop iteration. A fatal error, or `degraded_after` failures
+    // in a row, sends one "degraded" alert; the next success sends a "recovered" alert.
+    async fn settle(&mut self, result: anyhow::Result<()>) {
+        match result {
+            Ok(()) => {
+                if let Some(failures) = self.health.succeeded() {
+                    let message = self.templates.render_health(false, &self.health_context(failures, ""));
+                    self.send_health(&message).await;
+                }
+            }
+            Err(e) => {
+                let class = retry::classify(&e);
+                let degraded = self.health.failed(class, self.settings.degraded_after);
+                println_time!(
+                    "{} monitor on {} failed ({:?}, {} in a row): {:#}",
+                    self.checker.name(),
+                    self.checker.chain().name,
+                    class,
+                    self.health.consecutive_failures(),
+                    e
+                );
+                if degraded {
+                    let message = self.templates.render_health(true, &self.health_context(self.health.consecutive_failures(), &format!("{:#}", e)));
+                    self.send_health(&message).await;
+                }
+            }
+        }
+    }
+
+    fn health_context<'a>(&'a self, failures: u32, error: &'a str) -> HealthContext<'a> {
+        HealthContext {
+            chain: &self.checker.chain().name,
+            token: self.checker.name(),
+            failures,
+            error,
+        }
+    }
+
+    // Best effort, as the failure may well be Telegram itself
+    async fn send_health(&self, message: &str) {
+        if let Err(e) = self.send(message).await {
+            println_time!("Failed to send the monitor health alert: {:#}", e);
+        }
+    }
+
+    // Load the statuses persisted by the previous run
+    fn restore(&mut self) -> anyhow::Result<()> {
+        let persisted = self.store.load_statuses(self.chain_id(), self.checker.token())?;
+        for entry in &self.watchlist {
+            if let Some(status) = persisted.get(&entry.address) {
+                self.alerts.assume(entry.address, BlacklistState::from_status(*status));
+            }
+        }
+        println_time!("Restored the persisted status of {} watched address(es)", self.alerts.known_count());
+        Ok(())
+    }
+
+    /// Checks the current status of every watched address at `block` and alerts
+    /// on anything that changed while the monitor was not running.
+    async fn init(&mut self, block: u64) -> anyhow::Result<()> {
+        let Statuses { block, hash, statuses: initial } = self.fetch_statuses(block).await?;
+        for entry in self.watchlist.clone() {
+            match initial.get(&entry.address).copied().flatten() {
+                Some(status) => self.observe(entry.address, status, block, Some(hash), None).await?,
+                None => println_time!("Initial blacklist lookup failed for {} ({:?})", entry.label, entry.address),
+            }
+        }
+        self.store.set_last_checked_block(self.chain_id(), self.checker.token(), block)?;
+        Ok(())
+    }
+
+    // Replay the logs missed since the last run, then initialise from the current head.
+    // Replaying first means transitions are reported with the block they happened in.
+    async fn start(&mut self) -> anyhow::Result<u64> {
+        let head = self.head().await?;
+        if let Some(last_checked) = self.store.last_checked_block(self.chain_id(), self.checker.token())? {
+            if last_checked < head {
+                println_time!("Replaying blacklist logs for blocks {}..={} missed since the last run", last_checked + 1, head);
+                if let Err(e) = self.sync_logs(last_checked + 1, head).await {
+                    println_time!("Replay failed: {:#}", e);
+                }
+            }
+        }
+        self.init(head).await?;
+        Ok(head)
+    }
+
+    // Restore the persisted state, then run `start` until it succeeds, backing off between attempts
+    async fn start_resilient(&mut self) -> anyhow::Result<u64> {
+        self.restore()?;
+        let mut attempt = 0;
+        loop {
+            match self.start().await {
+                Ok(head) => {
+                    self.settle(Ok(())).await;
+                    return Ok(head);
+                }
+                Err(e) => {
+                    self.settle(Err(e)).await;
+                    tokio::time::sleep(self.retry.delay(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Periodically checks the blacklist status of every watched address with `isBlackListed`.
+    ///
+    /// Failed checks are retried on the next tick; the loop never stops on its own.
+    pub async fn run_polling(&mut self) -> anyhow::Result<()> {
+        self.restore()?;
+        let mut initialised = false;
+        loop {
+            self.reload();
+            let result = if initialised {
+                self.poll_once().await
+            } else {
+                self.init_at_head().await
+            };
+            initialised |= result.is_ok();
+            self.settle(result).await;
+
+            self.pause(self.settings.poll_interval).await;
+        }
+    }
+
+    async fn init_at_head(&mut self) -> anyhow::Result<()> {
+        let head = self.head().await?;
+        self.init(head).await
+    }
+
+    async fn poll_once(&mut self) -> anyhow::Result<()> {
+        let block = self.head().await?;
+        self.check_reorgs(block).await?;
+        let Statuses { block, hash, statuses } = self.fetch_statuses(block).await?;
+        for entry in self.watchlist.clone() {
+            let target = entry.address;
+            let Some(current_status) = statuses.get(&target).copied().flatten() else {
+                println_time!("Blacklist lookup failed for {} ({:?}) at block {}", entry.label, target, block);
+                continue;
+            };
+            self.observe(target, current_status, block, Some(hash), None).await?;
+        }
+        self.store.set_last_checked_block(self.chain_id(), self.checker.token(), block)?;
+        println_time!("Checked {} watched address(es) at block {}", self.watchlist.len(), block);
+        self.prepare_rescues().await;
+        self.flush_pending().await
+    }
+
+    /// Follows `AddedBlackList` / `RemovedBlackList` / `DestroyedBlackFunds` logs block by block.
+    ///
+    /// A full `isBlackListed` poll runs every poll interval, whenever the logs for a
+    /// range cannot be fetched and when addresses are added to the watchlist, so a
+    /// missed log never goes unnoticed.
+    pub async fn run_events(&mut self) -> anyhow::Result<()> {
+        let mut last_block = self.start_resilient().await?;
+        let mut last_poll = Instant::now();
+        loop {
+            self.pause(self.settings.block_interval).await;
+            self.reload();
+            let result = self.follow_logs(&mut last_block, &mut last_poll).await;
+            self.settle(result).await;
+        }
+    }
+
+    async fn follow_logs(&mut self, last_block: &mut u64, last_poll: &mut Instant) -> anyhow::Result<()> {
+        let head = self.head().await?;
+        if let Some(reorged) = self.check_reorgs(head).await? {
+            self.rewind(last_block, reorged);
+        }
+        let mut logs_failed = false;
+        if head > *last_block {
+            match self.sync_logs(*last_block + 1, head).await {
+                Ok(()) => *last_block = head,
+                Err(e) => {
+                    println_time!("{:#}, falling back to polling", e);
+                    logs_failed = true;
+                }
+            }
+        }
+
+        if logs_failed || self.reconcile_due || last_poll.elapsed() >= self.settings.poll_interval {
+            self.reconcile(head).await?;
+            *last_poll = Instant::now();
+        }
+        self.prepare_rescues().await;
+        self.flush_pending().await
+    }
+
+    /// Subscribes to new heads and blacklist logs over WebSocket.
+    ///
+    /// When the socket drops the monitor reconnects with exponential backoff and
+    /// replays the logs of every block mined while it was disconnected.
+    pub async fn run_websocket(&mut self, ws_url: &str) -> anyhow::Result<()> {
+        // Last block whose logs were all received; `start` read the logs up to its head
+        let mut checked = self.start_resilient().await?;
+        let mut reconnect = Reconnect::default();
+        loop {
+            self.reload();
+            let result = match self.follow_websocket(ws_url, &mut checked, &mut reconnect).await {
+                Ok(()) => Err(anyhow::anyhow!("WebSocket subscription closed")),
+                Err(e) => Err(e.context("WebSocket error")),
+            };
+            self.settle(result).await;
+            reconnect.wait().await;
+        }
+    }
+
+    async fn follow_websocket(
+        &mut self,
+        ws_url: &str,
+        checked: &mut u64,
+        reconnect: &mut Reconnect,
+    ) -> anyhow::Result<()> {
+        let ws = Provider::<Ws>::connect(ws_url)
+            .await
+            .context("Failed to connect to WebSocket provider")?;
+        let mut heads = ws.subscribe_blocks().await.context("Failed to subscribe to new heads")?;
+        let mut logs = ws
+            .subscribe_logs(&self.checker.event_filter())
+            .await
+            .context("Failed to subscribe to blacklist logs")?;
+        println_time!(
+            "Subscribed to new heads and {} freeze logs on {} over WebSocket",
+            self.checker.name(),
+            self.checker.chain().name
+        );
+        reconnect.connected();
+
+        // Replay anything mined while we were disconnected
+        let mut head = self.head().await?;
+        if head > *checked {
+            println_time!("Resyncing blacklist logs for blocks {}..={}", *checked + 1, head);
+            self.sync_logs(*checked + 1, head).await?;
+            *checked = head;
+        }
+        self.settle(Ok(())).await;
+
+        let fallback_interval = |interval: Duration| tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        let mut fallback = fallback_interval(self.settings.poll_interval);
+        let mut updates = self.config.clone();
+        loop {
+            tokio::select! {
+                block = heads.next() => {
+                    let Some(block) = block else { return Ok(()) };
+                    if let Some(number) = block.number {
+                        head = head.max(number.as_u64());
+                        // Logs of the newest head may still be in flight, so only its parent counts as checked
+                        *checked = (*checked).max(head.saturating_sub(1));
+                        self.store.set_last_checked_block(self.chain_id(), self.checker.token(), *checked)?;
+                    }
+                    // The subscription delivers the logs of the new branch itself
+                    self.check_reorgs(head).await?;
+                    self.prepare_rescues().await;
+                }
+                log = logs.next() => {
+                    let Some(log) = log else { return Ok(()) };
+                    if log.removed == Some(true) {
+                        // Let the logs that replace it through; the next head checks what it undid
+                        if let Some(number) = log.block_number {
+                            self.rewind(checked, number.as_u64());
+                        }
+                        continue;
+                    }
+                    if let Some(event) = self.checker.decode_log(&log) {
+                        self.store.record_event(&event)?;
+                        if self.directions(event.address).is_some() {
+                            self.apply_event(&event).await?;
+                        }
+                    }
+                }
+                _ = fallback.tick() => {
+                    self.reconcile(head).await?;
+                    self.flush_pending().await?;
+                }
+                Ok(()) = updates.changed() => {
+                    self.reload();
+                    if fallback.period() != self.settings.poll_interval {
+                        fallback = fallback_interval(self.settings.poll_interval);
+                    }
+                    if self.reconcile_due {
+                        self.reconcile(head).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    // Re-sign the pre-signed rescues of the frozen addresses whose state moved on
+    async fn prepare_rescues(&mut self) {
+        let frozen: Vec<Address> = self
+            .addresses()
+            .into_iter()
+            .filter(|address| self.alerts.state(*address) == BlacklistState::Blacklisted)
+            .collect();
+        if let Some(rescuer) = &mut self.rescuer {
+            rescuer.prepare(&frozen).await;
+        }
+    }
+
+    fn watched(&self) -> HashSet<Address> {
+        self.addresses().into_iter().collect()
+    }
+
+    // Read the logs again from `block` on, as the canonical chain changed there
+    fn rewind(&mut self, last_block: &mut u64, block: u64) {
+        *last_block = (*last_block).min(block.saturating_sub(1));
+        self.last_event = self.last_event.filter(|(last, _)| *last < block);
+    }
+
+    // Fetch the blacklist logs of blocks `from..=to`, store them and apply the watched ones
+    async fn sync_logs(&mut self, from: u64, to: u64) -> anyhow::Result<()> {
+        let this = &*self;
+        let events = self
+            .retry
+            .run("Fetching freeze logs", move || async move {
+                blacklist_events::fetch_events(this.client.clone(), this.checker.as_ref(), from, to).await
+            })
+            .await?;
+        let watched = self.watched();
+        for event in events {
+            self.store.record_event(&event)?;
+            if watched.contains(&event.address) {
+                self.apply_event(&event).await?;
+            }
+        }
+        self.store.extend_synced(self.chain_id(), self.checker.token(), from, to)?;
+        self.store.set_last_checked_block(self.chain_id(), self.checker.token(), to)?;
+        Ok(())
+    }
+
+    async fn apply_event(&mut self, event: &BlacklistEvent) -> anyhow::Result<()> {
+        let position = (event.block_number, event.log_index);
+        if self.last_event.is_some_and(|last| position <= last) {
+            return Ok(());
+        }
+        self.last_event = Some(position);
+
+        let label = self.label(event.address);
+        match event.change {
+            BlacklistChange::Added => {
+                println_time!("Address {} ({:?}) added to {} blacklist in block {} (tx {:?})", label, event.address, self.checker.name(), event.block_number, event.tx_hash);
+                // An addition proves the address was not blacklisted right before it
+                if self.alerts.state(event.address) == BlacklistState::Unknown {
+                    self.alerts.assume(event.address, BlacklistState::Cleared);
+                }
+                self.observe(event.address, true, event.block_number, event.block_hash, Some(event.tx_hash)).await?;
+            }
+            BlacklistChange::Removed => {
+                println_time!("Address {} ({:?}) removed from {} blacklist in block {} (tx {:?})", label, event.address, self.checker.name(), event.block_number, event.tx_hash);
+                // A removal proves the address was blacklisted right before it
+                if self.alerts.state(event.address) == BlacklistState::Unknown {
+                    self.alerts.assume(event.address, BlacklistState::Blacklisted);
+                }
+                self.observe(event.address, false, event.block_number, event.block_hash, Some(event.tx_hash)).await?;
+            }
+            BlacklistChange::Destroyed(amount) => {
+                println_time!("Black funds of {} ({:?}) destroyed in block {}: {} {} units (tx {:?})", label, event.address, event.block_number, amount, self.checker.name(), event.tx_hash);
+                let alerted = self.directions(event.address).is_some_and(|d| d.on_destroy);
+                if alerted {
+                    self.notify(event.address, AlertKind::Destroyed, event.block_number, Some(amount)).await?;
+                }
+                if let Some(hash) = event.block_hash {
+                    self.unconfirmed.push(Unconfirmed {
+                        address: event.address,
+                        change: event.change,
+                        block: event.block_number,
+                        hash,
+                        tx: Some(event.tx_hash),
+                        alerted,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Compare the polled status with what the logs told us and alert on anything missed
+    async fn reconcile(&mut self, block: u64) -> anyhow::Result<()> {
+        let Statuses { block, hash, statuses } = self.fetch_statuses(block).await?;
+        self.reconcile_due = false;
+        for (address, status) in statuses {
+            let Some(current_status) = status else {
+                println_time!("Blacklist lookup failed for {} ({:?}) at block {}", self.label(address), address, block);
+                continue;
+            };
+            self.observe(address, current_status, block, Some(hash), None).await?;
+        }
+        Ok(())
+    }
+
+    // Check that the changes seen in blocks that are not final yet are still on the canonical
+    // chain, forgetting them once they are `confirmations` deep. Returns the lowest block
+    // found reorged out, from which the logs have to be read again.
+    async fn check_reorgs(&mut self, head: u64) -> anyhow::Result<Option<u64>> {
+        let depth = self.checker.chain().confirmations.max(1);
+        let mut reorged: Option<u64> = None;
+        let mut index = 0;
+        while let Some(change) = self.unconfirmed.get(index).cloned() {
+            match self.block_hash(change.block).await? {
+                Some(hash) if hash == change.hash => {
+                    if head + 1 >= change.block + depth {
+                        self.unconfirmed.remove(index);
+                    } else {
+                        index += 1;
+                    }
+                    continue;
+                }
+                // The endpoint has not seen the block yet, so it is checked again on the next pass
+                None => {
+                    index += 1;
+                    continue;
+                }
+                Some(_) => {}
+            }
+            self.reorged(&change, head).await?;
+            self.unconfirmed.remove(index);
+            reorged = Some(reorged.map_or(change.block, |block| block.min(change.block)));
+        }
+        Ok(reorged)
+    }
+
+    // Look a change up again on the canonical chain after its block was reorged out. One
+    // that was mined again is tracked from where it is now; one that is gone is undone and
+    // its alert followed by a "reorged out" alert.
+    async fn reorged(&mut self, change: &Unconfirmed, head: u64) -> anyhow::Result<()> {
+        let address = change.address;
+        if self.directions(address).is_none() {
+            return Ok(());
+        }
+        let label = self.label(address);
+        let mined_again = match change.change {
+            BlacklistChange::Destroyed(_) => {
+                let tx = change.tx.ok_or_else(|| anyhow!("Destroyed funds without a transaction"))?;
+                let client = &self.client;
+                let receipt = self
+                    .retry
+                    .run("Fetching the receipt", move || async move {
+                        client.get_transaction_receipt(tx).await.context("Failed to fetch receipt")
+                    })
+                    .await?;
+                receipt.and_then(|receipt| Some((receipt.block_number?.as_u64(), receipt.block_hash?)))
+            }
+            BlacklistChange::Added | BlacklistChange::Removed => {
+                let Statuses { block, hash, statuses } = self.fetch_statuses_of(&[address], head).await?;
+                let blacklisted = statuses
+                    .get(&address)
+                    .copied()
+                    .flatten()
+                    .ok_or_else(|| anyhow!("Blacklist lookup failed for {} ({:?}) at block {}", label, address, block))?;
+                if blacklisted == (change.change == BlacklistChange::Added) {
+                    Some((block, hash))
+                } else {
+                    self.store.save_status(self.chain_id(), self.checker.token(), address, blacklisted, block)?;
+                    self.alerts.assume(address, BlacklistState::from_status(blacklisted));
+                    None
+                }
+            }
+        };
+        if let Some((block, hash)) = mined_again {
+            println_time!("Block {} was reorged out, but the change of {} ({:?}) still holds at block {}", change.block, label, address, block);
+            self.unconfirmed.push(Unconfirmed { block, hash, ..change.clone() });
+            return Ok(());
+        }
+        println_time!(
+            "Block {} was reorged out and took the change of {} ({:?}) with it, the address is {:?}",
+            change.block,
+            label,
+            address,
+            self.alerts.state(address)
+        );
+        if let Some(tx) = change.tx {
+            self.store.forget_events(self.chain_id(), tx)?;
+        }
+        if change.alerted {
+            let amount = match change.change {
+                BlacklistChange::Destroyed(amount) => Some(amount),
+                _ => None,
+            };
+            self.notify(address, AlertKind::ReorgedOut, change.block, amount).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::sync::Mutex;
+
+    use crate::client::ChainProvider;
+    use crate::config;
+    use crate::freeze;
+    use crate::testing::{self, rpc_server, Requests, RpcHandler};
+
+    const ADDRESS: Address = Address::repeat_byte(0xaa);
+
+    // Chain state answered by the mock node
+    #[derive(Default)]
+    struct Node {
+        hashes: HashMap<u64, H256>,
+        frozen: bool,
+    }
+
+    fn block(number: u64, hash: H256) -> Value {
+        json!({ "number": U64::from(number), "hash": hash })
+    }
+
+    async fn monitor(node: Arc<Mutex<Node>>) -> (Monitor<ChainProvider>, Requests) {
+        let handler: Arc<RpcHandler> = Arc::new(move |method, params| {
+            let node = node.lock().unwrap();
+            match method {
+                "eth_getBlockByNumber" => {
+                    let number = params[0].as_str().and_then(|n| u64::from_str_radix(n.trim_start_matches("0x"), 16).ok());
+                    let latest = node.hashes.keys().max().copied();
+                    Ok(number
+                        .or(latest)
+                        .and_then(|number| node.hashes.get(&number).map(|hash| block(number, *hash)))
+                        .unwrap_or(Value::Null))
+                }
+                "eth_call" => Ok(testing::aggregate3(vec![(true, testing::frozen(node.frozen))])),
+                _ => Err(json!({ "code": -32601, "message": "method not found" })),
+            }
+        });
+        let (url, _) = rpc_server(handler).await;
+        let (config, problems) = config::from_toml(&format!(
+            r#"
+            [chains.42161]
+            rpc_urls = ["{url}"]
+
+            [retry]
+            attempts = 1
+
+            [[watchlist.addresses]]
+            address = "{ADDRESS:?}"
+            label = "treasury"
+            alerts = "add+remove"
+
+            [notifiers.telegram]
+            bot_token = "token"
+            chat_id = "chat"
+            "#
+        ));
+        assert!(problems.is_empty(), "{:?}", problems);
+        let chain = &config.chains[0];
+        let checker = freeze::checkers(&chain.chain, &chain.issuers).unwrap().remove(0);
+        let client = Arc::new(testing::provider(&url, 42161));
+        let (_, updates) = watch::channel(Arc::new(config));
+        let mut monitor = Monitor::new(client, checker, Arc::new(Store::in_memory()), updates, None);
+        let (telegram, messages) = testing::telegram(200).await;
+        monitor.telegram = telegram;
+        (monitor, messages)
+    }
+
+    fn added_at(block: u64, hash: H256) -> Unconfirmed {
+        Unconfirmed { address: ADDRESS, change: BlacklistChange::Added, block, hash, tx: None, alerted: true }
+    }
+
+    #[tokio::test]
+    async fn a_block_the_node_has_not_seen_is_lag() {
+        let node = Arc::new(Mutex::new(Node::default()));
+        let (mut monitor, messages) = monitor(node.clone()).await;
+        monitor.alerts.assume(ADDRESS, BlacklistState::Blacklisted);
+        monitor.unconfirmed.push(added_at(10, H256::repeat_byte(1)));
+
+        assert_eq!(monitor.check_reorgs(12).await.unwrap(), None);
+        assert_eq!(monitor.unconfirmed.len(), 1);
+        assert_eq!(monitor.alerts.state(ADDRESS), BlacklistState::Blacklisted);
+        assert!(testing::messages(&messages).is_empty());
+
+        // Forgotten once the endpoint has it, at the confirmation depth
+        node.lock().unwrap().hashes.insert(10, H256::repeat_byte(1));
+        let depth = monitor.checker.chain().confirmations.max(1);
+        assert_eq!(monitor.check_reorgs(10 + depth - 2).await.unwrap(), None);
+        assert_eq!(monitor.unconfirmed.len(), 1);
+        assert_eq!(monitor.check_reorgs(10 + depth - 1).await.unwrap(), None);
+        assert!(monitor.unconfirmed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_changed_hash_reports_the_alert_as_reorged_out() {
+        let node = Arc::new(Mutex::new(Node::default()));
+        node.lock().unwrap().hashes.extend([(10, H256::repeat_byte(2)), (12, H256::repeat_byte(3))]);
+        let (mut monitor, messages) = monitor(node).await;
+        monitor.alerts.assume(ADDRESS, BlacklistState::Blacklisted);
+        monitor.unconfirmed.push(added_at(10, H256::repeat_byte(1)));
+
+        assert_eq!(monitor.check_reorgs(12).await.unwrap(), Some(10));
+        assert!(monitor.unconfirmed.is_empty());
+        assert_eq!(monitor.alerts.state(ADDRESS), BlacklistState::Cleared);
+        let messages = testing::messages(&messages);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("Block 10 on Arbitrum One was reorged out"), "{}", messages[0]);
+        assert!(monitor.store.alert_sent(42161, monitor.checker.token(), ADDRESS, "reorged_out", 10).unwrap());
+    }
+
+    #[tokio::test]
+    async fn a_change_mined_again_is_tracked_at_its_new_block() {
+        let node = Arc::new(Mutex::new(Node { frozen: true, ..Default::default() }));
+        node.lock().unwrap().hashes.extend([(10, H256::repeat_byte(2)), (12, H256::repeat_byte(3))]);
+        let (mut monitor, messages) = monitor(node).await;
+        monitor.alerts.assume(ADDRESS, BlacklistState::Blacklisted);
+        monitor.unconfirmed.push(added_at(10, H256::repeat_byte(1)));
+
+        assert_eq!(monitor.check_reorgs(12).await.unwrap(), Some(10));
+        assert_eq!(monitor.alerts.state(ADDRESS), BlacklistState::Blacklisted);
+        assert!(testing::messages(&messages).is_empty());
+        let tracked = &monitor.unconfirmed[0];
+        assert_eq!((tracked.block, tracked.hash), (12, H256::repeat_byte(3)));
+    }
+
+    #[tokio::test]
+    async fn reminders_only_follow_a_delivered_alert() {
+        let (mut monitor, _) = monitor(Arc::new(Mutex::new(Node::default()))).await;
+        let (rejecting, rejected) = testing::telegram(400).await;
+        monitor.telegram = rejecting;
+        monitor.alerts = AlertTracker::new(Some(Duration::ZERO));
+        monitor.alerts.assume(ADDRESS, BlacklistState::Cleared);
+        let token = monitor.checker.token();
+
+        monitor.observe(ADDRESS, true, 20, None, None).await.unwrap();
+        assert_eq!(testing::messages(&rejected).len(), 1);
+        assert_eq!(monitor.pending.len(), 1);
+        assert!(!monitor.store.alert_sent(42161, token, ADDRESS, AlertKind::Added.as_str(), 20).unwrap());
+        // Nothing was delivered, so there is nothing to remind of yet
+        monitor.observe(ADDRESS, true, 21, None, None).await.unwrap();
+        assert_eq!(testing::messages(&rejected).len(), 1);
+
+        let (telegram, delivered) = testing::telegram(200).await;
+        monitor.telegram = telegram;
+        monitor.flush_pending().await.unwrap();
+        assert!(monitor.store.alert_sent(42161, token, ADDRESS, AlertKind::Added.as_str(), 20).unwrap());
+        monitor.observe(ADDRESS, true, 22, None, None).await.unwrap();
+        let messages = testing::messages(&delivered);
+        assert_eq!(messages.len(), 2);
+        assert!(monitor.store.alert_sent(42161, token, ADDRESS, "reminder", 22).unwrap());
+    }
+
+    #[tokio::test]
+    async fn reminders_respect_the_alert_directions() {
+        let (mut monitor, messages) = monitor(Arc::new(Mutex::new(Node::default()))).await;
+        monitor.alerts = AlertTracker::new(Some(Duration::ZERO));
+        monitor.alerts.assume(ADDRESS, BlacklistState::Cleared);
+        monitor.observe(ADDRESS, true, 20, None, None).await.unwrap();
+        assert_eq!(testing::messages(&messages).len(), 1);
+
+        // The watchlist no longer wants additions
+        monitor.watchlist[0].alerts.on_add = false;
+        monitor.observe(ADDRESS, true, 21, None, None).await.unwrap();
+        assert_eq!(testing::messages(&messages).len(), 1);
+        assert!(!monitor.store.alert_sent(42161, monitor.checker.token(), ADDRESS, "reminder", 21).unwrap());
+    }
+}
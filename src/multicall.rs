@@ -0,0 +1,179 @@
+use anyhow::Context;
+use ethers::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::contracts::{Call3, Multicall3, MULTICALL3_ADDRESS};
+use crate::freeze::FreezeChecker;
+
+// Keep each aggregate call well below typical eth_call gas and response limits
+const MAX_CALLS_PER_BATCH: usize = 500;
+
+/// Checks the freeze status of many addresses with Multicall3 `aggregate3`.
+///
+/// Every sub-call is sent with `allowFailure = true`, so one reverting or malformed
+/// lookup does not fail the whole batch. All chunks are pinned to `block` so the
+/// statuses are consistent with each other; a block hash also pins them to one fork.
+///
+/// # Returns
+/// A map from address to `Some(status)`, or `None` when that lookup failed.
+pub async fn batch_is_frozen<M: Middleware + 'static>(
+    client: Arc<M>,
+    checker: &dyn FreezeChecker,
+    addresses: &[Address],
+    block: BlockId,
+) -> anyhow::Result<HashMap<Address, Option<bool>>> {
+    let multicall = Multicall3::new(
+        MULTICALL3_ADDRESS.parse::<Address>()?,
+        client,
+    );
+
+    let mut statuses = HashMap::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_CALLS_PER_BATCH) {
+        let calls = chunk
+            .iter()
+            .map(|address| Call3 {
+                target: checker.token(),
+                allow_failure: true,
+                call_data: checker.encode_check(*address),
+            })
+            .collect::<Vec<_>>();
+
+        let results = multicall
+            .aggregate_3(calls)
+            .block(block)
+            .call()
+            .await
+            .map_err(|e| match e {
+                // Keep the middleware error itself so retries can tell a timeout from a revert
+                ContractError::MiddlewareError { e } => anyhow::Error::new(e),
+                ContractError::ProviderError { e } => anyhow::Error::new(e),
+                e => anyhow::Error::new(e),
+            })
+            .context("Multicall3 aggregate3 call failed")?;
+
+        for (address, (success, return_data)) in chunk.iter().zip(results) {
+            let status = if success {
+                checker.decode_check(&return_data)
+            } else {
+                None
+            };
+            statuses.insert(*address, status);
+        }
+    }
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{self, ParamType, Token};
+    use serde_json::json;
+
+    use crate::chains;
+    use crate::freeze::{CheckerSpec, FreezeList, SignatureChecker};
+    use crate::testing::{self, calls, rpc_server, RpcHandler};
+
+    const TOKEN: Address = Address::repeat_byte(0x70);
+
+    fn checker() -> SignatureChecker {
+        let chain = Arc::new(chains::builtin(42161).unwrap());
+        SignatureChecker::new(CheckerSpec::builtin("USDT", FreezeList::TetherV2, TOKEN), chain).unwrap()
+    }
+
+    fn data(params: &serde_json::Value) -> Vec<u8> {
+        let data = params[0]["data"].as_str().or(params[0]["input"].as_str()).unwrap_or_default();
+        hex::decode(data.trim_start_matches("0x")).unwrap()
+    }
+
+    // The (target, allowFailure, callData) sub-calls of an aggregate3 call
+    fn sub_calls(data: &[u8]) -> Vec<(Address, bool, Vec<u8>)> {
+        let call3 = ParamType::Tuple(vec![ParamType::Address, ParamType::Bool, ParamType::Bytes]);
+        let decoded = abi::decode(&[ParamType::Array(Box::new(call3))], &data[4..]).unwrap();
+        let Token::Array(calls) = &decoded[0] else { panic!("not an array") };
+        calls
+            .iter()
+            .map(|call| match call {
+                Token::Tuple(fields) => match &fields[..] {
+                    [Token::Address(target), Token::Bool(allow_failure), Token::Bytes(data)] => (*target, *allow_failure, data.clone()),
+                    _ => panic!("unexpected Call3 {:?}", fields),
+                },
+                _ => panic!("unexpected Call3 {:?}", call),
+            })
+            .collect()
+    }
+
+    fn word(value: u64) -> String {
+        format!("{:064x}", value)
+    }
+
+    #[tokio::test]
+    async fn checks_500_addresses_per_aggregate3_call() {
+        // Every address with an even last byte is frozen
+        let handler: Arc<RpcHandler> = Arc::new(|_, params| {
+            let results = sub_calls(&data(params)).into_iter().map(|(_, _, data)| (true, testing::frozen(data[35] % 2 == 0))).collect();
+            Ok(testing::aggregate3(results))
+        });
+        let (url, received) = rpc_server(handler).await;
+        let checker = checker();
+        let addresses: Vec<Address> = (0..1001u64).map(Address::from_low_u64_be).collect();
+        let hash = H256::repeat_byte(7);
+
+        let statuses = batch_is_frozen(Arc::new(testing::provider(&url, 42161)), &checker, &addresses, BlockId::Hash(hash)).await.unwrap();
+
+        let requests = calls(&received, "eth_call");
+        let sizes: Vec<usize> = requests.iter().map(|request| sub_calls(&data(&request.json()["params"])).len()).collect();
+        assert_eq!(sizes, [500, 500, 1]);
+        for request in &requests {
+            let params = &request.json()["params"];
+            assert_eq!(params[0]["to"], json!(MULTICALL3_ADDRESS.to_lowercase()));
+            // Pinned to the block hash
+            assert!(params[1].to_string().contains(&format!("{:?}", hash)), "{}", params[1]);
+        }
+        let first = sub_calls(&data(&requests[0].json()["params"]));
+        assert_eq!(first[1], (TOKEN, true, checker.encode_check(addresses[1]).to_vec()));
+
+        assert_eq!(statuses.len(), addresses.len());
+        for (i, address) in addresses.iter().enumerate() {
+            assert_eq!(statuses[address], Some(i % 2 == 0), "address #{}", i);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_failed_lookup_is_unknown_rather_than_not_frozen() {
+        // aggregate3 returning [(true, true), (false, Error selector), (true, "")]
+        let words = [
+            word(0x20),
+            word(3),
+            word(0x60),
+            word(0xe0),
+            word(0x160),
+            word(1),
+            word(0x40),
+            word(32),
+            word(1),
+            word(0),
+            word(0x40),
+            word(4),
+            format!("08c379a0{}", "0".repeat(56)),
+            word(1),
+            word(0x40),
+            word(0),
+        ];
+        let encoded = format!("0x{}", words.concat());
+        assert_eq!(
+            json!(encoded),
+            testing::aggregate3(vec![(true, testing::frozen(true)), (false, vec![0x08, 0xc3, 0x79, 0xa0]), (true, Vec::new())])
+        );
+        let handler: Arc<RpcHandler> = Arc::new(move |_, _| Ok(json!(encoded)));
+        let (url, _) = rpc_server(handler).await;
+        let addresses = [Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3)];
+
+        let statuses = batch_is_frozen(Arc::new(testing::provider(&url, 42161)), &checker(), &addresses, BlockNumber::Latest.into())
+            .await
+            .unwrap();
+        assert_eq!(statuses[&addresses[0]], Some(true));
+        assert_eq!(statuses[&addresses[1]], None);
+        assert_eq!(statuses[&addresses[2]], None);
+    }
+}
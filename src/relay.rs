@@ -0,0 +1,312 @@
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use serde_json::{json, Value};
+use std::env;
+use std::time::Duration;
+
+use crate::client::{self, ChainProvider};
+use crate::error::{Error, Result};
+use crate::rpc;
+
+/// Key that signs relay requests (`X-Flashbots-Signature`). It only identifies the
+/// sender to the relays and never holds funds; a random key is used when it is unset.
+pub const RELAY_AUTH_KEY_VAR: &str = "RELAY_AUTH_KEY";
+
+// How often inclusion is checked while the relays hold a transaction
+const INCLUSION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayMethod {
+    // eth_sendBundle with the transaction alone, once per target block
+    Bundle,
+    // eth_sendPrivateTransaction with the last target block as maxBlockNumber
+    PrivateTransaction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelaySettings {
+    pub method: RelayMethod,
+    // Blocks after the current head the relays may include a transaction in
+    pub target_blocks: u64,
+    // Broadcast publicly when no relay takes a transaction or it is not included in time
+    pub fallback_public: bool,
+}
+
+/// Flashbots-style private relays of one chain, which keep sweeps and rescues out of the
+/// public mempool until they are mined.
+#[derive(Clone)]
+pub struct Relay {
+    // Parsed with their names, see `rpc::parse_url`
+    endpoints: Vec<(reqwest::Url, String)>,
+    settings: RelaySettings,
+    auth: LocalWallet,
+    http: reqwest::Client,
+}
+
+impl Relay {
+    pub fn new(urls: Vec<String>, settings: RelaySettings, timeout: Duration) -> Result<Self> {
+        let auth = match env::var(RELAY_AUTH_KEY_VAR).ok().filter(|key| !key.trim().is_empty()) {
+            Some(key) => client::parse_wallet(&key, RELAY_AUTH_KEY_VAR)?,
+            None => LocalWallet::new(&mut rand::thread_rng()),
+        };
+        Self::with_auth(urls, settings, auth, timeout)
+    }
+
+    fn with_auth(urls: Vec<String>, settings: RelaySettings, auth: LocalWallet, timeout: Duration) -> Result<Self> {
+        let endpoints = urls
+            .iter()
+            .enumerate()
+            .map(|(index, url)| rpc::parse_url(url).ok_or_else(|| Error::Config(format!("invalid relay URL #{}", index + 1))))
+            .collect::<Result<_>>()?;
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| Error::Config(format!("relay client: {}", e)))?;
+        Ok(Relay { endpoints, settings, auth, http })
+    }
+
+    pub fn settings(&self) -> RelaySettings {
+        self.settings
+    }
+
+    /// Hands a signed transaction to every relay for the target blocks after the current
+    /// head, and returns the last of them. Fails when no relay accepted it.
+    pub async fn send(&self, provider: &ChainProvider, raw: &Bytes) -> Result<u64> {
+        let head = provider.get_block_number().await?.as_u64();
+        let last = head + self.settings.target_blocks;
+        let requests: Vec<(&str, Value)> = match self.settings.method {
+            RelayMethod::Bundle => (head + 1..=last)
+                .map(|block| ("eth_sendBundle", json!([{ "txs": [raw], "blockNumber": U64::from(block) }])))
+                .collect(),
+            RelayMethod::PrivateTransaction => vec![(
+                "eth_sendPrivateTransaction",
+                json!([{ "tx": raw, "maxBlockNumber": U64::from(last), "preferences": { "fast": true } }]),
+            )],
+        };
+        let mut accepted = 0;
+        let mut last_error = None;
+        for (url, name) in &self.endpoints {
+            let mut took_it = false;
+            for (method, params) in &requests {
+                match self.call(url, method, params).await {
+                    Ok(()) => took_it = true,
+                    Err(e) => {
+                        println_time!("Relay {} rejected {}: {}", name, method, e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+            accepted += usize::from(took_it);
+        }
+        if accepted == 0 {
+            return Err(last_error.unwrap_or_else(|| Error::Relay("no relay configured".to_string())));
+        }
+        println_time!("Sent to {} of {} relay(s) for blocks {}..={}", accepted, self.endpoints.len(), head + 1, last);
+        Ok(last)
+    }
+
+    /// Sends a signed transaction through the relays and waits until it is mined or the
+    /// target blocks have passed, then broadcasts it publicly if that is allowed.
+    pub async fn submit(&self, provider: &ChainProvider, raw: &Bytes, tx_hash: TxHash) -> Result<()> {
+        match self.send(provider, raw).await {
+            Ok(last) => {
+                if included_by(provider, tx_hash, last).await? {
+                    return Ok(());
+                }
+                println_time!("Transaction {:?} was not included by block {}", tx_hash, last);
+            }
+            Err(e) => println_time!("No relay took transaction {:?}: {}", tx_hash, e),
+        }
+        if !self.settings.fallback_public {
+            return Err(Error::Relay(format!("transaction {:?} was not included through the relays", tx_hash)));
+        }
+        println_time!("Broadcasting transaction {:?} publicly", tx_hash);
+        publish(provider, raw, tx_hash).await
+    }
+
+    // JSON-RPC call signed the way Flashbots expects: the hex keccak256 of the body,
+    // signed as a personal message, next to the signing address
+    async fn call(&self, url: &reqwest::Url, method: &str, params: &Value) -> Result<()> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+        let signature = self
+            .auth
+            .sign_message(format!("{:?}", H256::from(keccak256(body.as_bytes()))))
+            .await
+            .map_err(|e| Error::Signing(e.to_string()))?;
+        let response = self
+            .http
+            .post(url.clone())
+            .header("Content-Type", "application/json")
+            .header("X-Flashbots-Signature", format!("{:?}:0x{}", self.auth.address(), signature))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| Error::Relay(e.without_url().to_string()))?;
+        let status = response.status();
+        let reply: Value = response
+            .json()
+            .await
+            .map_err(|e| Error::Relay(format!("HTTP {}: {}", status, e.without_url())))?;
+        match reply.get("error") {
+            Some(error) => Err(Error::Relay(error.to_string())),
+            None if !status.is_success() => Err(Error::Relay(format!("HTTP {}", status))),
+            None => Ok(()),
+        }
+    }
+}
+
+// Whether the transaction is mined by the end of block `last`
+async fn included_by(provider: &ChainProvider, tx_hash: TxHash, last: u64) -> Result<bool> {
+    loop {
+        // The head is read first, so a receipt missing afterwards is missing at that head
+        let head = provider.get_block_number().await?.as_u64();
+        if provider.get_transaction_receipt(tx_hash).await?.is_some() {
+            return Ok(true);
+        }
+        if head > last {
+            return Ok(false);
+        }
+        tokio::time::sleep(INCLUSION_POLL_INTERVAL).await;
+    }
+}
+
+/// Broadcasts a signed transaction through the public mempool. A transaction the node
+/// refuses because it was mined in the meantime counts as sent.
+pub async fn publish(provider: &ChainProvider, raw: &Bytes, tx_hash: TxHash) -> Result<()> {
+    match provider.send_raw_transaction(raw.clone()).await {
+        Ok(_) => Ok(()),
+        Err(e) => match provider.get_transaction_receipt(tx_hash).await {
+            Ok(Some(_)) => Ok(()),
+            _ => Err(e.into()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use crate::testing::{self, calls, rpc_server, RpcHandler};
+
+    const AUTH_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const HEAD: u64 = 16;
+
+    // Chain node at HEAD that knows the receipt of the transaction when `mined` is set,
+    // and relays that accept everything unless `reject` is set
+    fn node(mined: bool, reject: bool) -> Arc<RpcHandler> {
+        Arc::new(move |method, _params| match method {
+            "eth_blockNumber" => Ok(json!(U64::from(HEAD))),
+            "eth_getTransactionReceipt" if mined => Ok(receipt()),
+            "eth_getTransactionReceipt" => Ok(Value::Null),
+            "eth_sendRawTransaction" => Ok(json!(tx_hash())),
+            "eth_sendBundle" | "eth_sendPrivateTransaction" if reject => Err(json!({ "code": -32000, "message": "rejected" })),
+            "eth_sendBundle" => Ok(json!({ "bundleHash": H256::zero() })),
+            "eth_sendPrivateTransaction" => Ok(json!(tx_hash())),
+            _ => Err(json!({ "code": -32601, "message": "method not found" })),
+        })
+    }
+
+    fn tx_hash() -> TxHash {
+        H256::repeat_byte(0xab)
+    }
+
+    fn raw() -> Bytes {
+        Bytes::from(vec![0x02, 0xf8, 0x01])
+    }
+
+    fn receipt() -> Value {
+        json!({
+            "transactionHash": tx_hash(), "transactionIndex": "0x0", "blockHash": H256::repeat_byte(0x11),
+            "blockNumber": U64::from(HEAD), "from": Address::zero(), "to": Address::zero(),
+            "cumulativeGasUsed": "0x5208", "gasUsed": "0x5208", "contractAddress": null, "logs": [],
+            "logsBloom": Bloom::zero(), "status": "0x1", "effectiveGasPrice": "0x1", "type": "0x2"
+        })
+    }
+
+    fn provider(url: &str) -> ChainProvider {
+        testing::provider(url, 1)
+    }
+
+    fn relay(url: &str, method: RelayMethod, fallback_public: bool) -> Relay {
+        let settings = RelaySettings { method, target_blocks: 3, fallback_public };
+        let auth = client::parse_wallet(AUTH_KEY, "key").unwrap();
+        Relay::with_auth(vec![url.to_string()], settings, auth, Duration::from_secs(5)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn signs_requests_with_the_auth_key() {
+        let (url, received) = rpc_server(node(false, false)).await;
+        relay(&url, RelayMethod::PrivateTransaction, false).send(&provider(&url), &raw()).await.unwrap();
+
+        let request = &calls(&received, "eth_sendPrivateTransaction")[0];
+        let header = &request.headers["x-flashbots-signature"];
+        let (address, signature) = header.split_once(':').unwrap();
+        let auth = client::parse_wallet(AUTH_KEY, "key").unwrap();
+        assert_eq!(address.parse::<Address>().unwrap(), auth.address());
+        let message = format!("{:?}", H256::from(keccak256(request.body.as_bytes())));
+        let signature = Signature::from_str(signature.trim_start_matches("0x")).unwrap();
+        assert_eq!(signature.recover(message).unwrap(), auth.address());
+    }
+
+    #[tokio::test]
+    async fn bundle_targets_every_block_after_the_head() {
+        let (url, received) = rpc_server(node(false, false)).await;
+        let last = relay(&url, RelayMethod::Bundle, false).send(&provider(&url), &raw()).await.unwrap();
+
+        assert_eq!(last, HEAD + 3);
+        let params: Vec<Value> = calls(&received, "eth_sendBundle").iter().map(|request| request.json()["params"][0].clone()).collect();
+        let blocks: Vec<&Value> = params.iter().map(|params| &params["blockNumber"]).collect();
+        assert_eq!(blocks, [&json!("0x11"), &json!("0x12"), &json!("0x13")]);
+        assert!(params.iter().all(|params| params["txs"] == json!([raw()])));
+    }
+
+    #[tokio::test]
+    async fn private_transaction_is_valid_until_the_last_target_block() {
+        let (url, received) = rpc_server(node(false, false)).await;
+        relay(&url, RelayMethod::PrivateTransaction, false).send(&provider(&url), &raw()).await.unwrap();
+
+        let requests = calls(&received, "eth_sendPrivateTransaction");
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].json()["params"],
+            json!([{ "tx": raw(), "maxBlockNumber": "0x13", "preferences": { "fast": true } }])
+        );
+    }
+
+    #[tokio::test]
+    async fn send_fails_when_no_relay_accepts() {
+        let (url, _) = rpc_server(node(false, true)).await;
+        let result = relay(&url, RelayMethod::PrivateTransaction, false).send(&provider(&url), &raw()).await;
+        assert!(matches!(result, Err(Error::Relay(_))));
+    }
+
+    #[tokio::test]
+    async fn included_by_checks_the_receipt_up_to_the_last_block() {
+        let (url, _) = rpc_server(node(true, false)).await;
+        assert!(included_by(&provider(&url), tx_hash(), HEAD).await.unwrap());
+
+        let (url, _) = rpc_server(node(false, false)).await;
+        assert!(!included_by(&provider(&url), tx_hash(), HEAD - 1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn submit_broadcasts_publicly_when_no_relay_takes_it() {
+        let (url, received) = rpc_server(node(false, true)).await;
+        relay(&url, RelayMethod::PrivateTransaction, true).submit(&provider(&url), &raw(), tx_hash()).await.unwrap();
+
+        let broadcasts = calls(&received, "eth_sendRawTransaction");
+        assert_eq!(broadcasts.len(), 1);
+        assert_eq!(broadcasts[0].json()["params"], json!([raw()]));
+    }
+
+    #[tokio::test]
+    async fn submit_without_fallback_never_broadcasts() {
+        let (url, received) = rpc_server(node(false, true)).await;
+        let result = relay(&url, RelayMethod::PrivateTransaction, false).submit(&provider(&url), &raw(), tx_hash()).await;
+
+        assert!(matches!(result, Err(Error::Relay(_))));
+        assert!(calls(&received, "eth_sendRawTransaction").is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+use crate::config::{self, Config};
+
+// How often the configuration and watchlist files are checked for changes
+const FILE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Reloads the configuration whenever its file or the watchlist file changes, or the
+/// process receives SIGHUP, and publishes every valid new configuration.
+///
+/// Watchlists, intervals, thresholds, retries, rescue and notifier settings apply live. Chains,
+/// RPC and relay settings, the database, the monitor mode, mempool watching, enabling the
+/// rescue and the settings of the other commands (chain id, sweep) keep their startup values
+/// until the next restart. An invalid configuration is reported and ignored.
+pub fn spawn(initial: Arc<Config>) -> anyhow::Result<watch::Receiver<Arc<Config>>> {
+    let (sender, receiver) = watch::channel(initial.clone());
+    let mut hangups = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        let mut current = initial;
+        let mut modified = modification_times(&current);
+        let mut ticks = tokio::time::interval(FILE_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = hangups.recv() => println_time!("Received SIGHUP, reloading the configuration"),
+                _ = ticks.tick() => {
+                    let now = modification_times(&current);
+                    if now == modified {
+                        continue;
+                    }
+                    modified = now;
+                    println_time!("Configuration files changed, reloading the configuration");
+                }
+            }
+            let Some(next) = reload(&current) else { continue };
+            current = Arc::new(next);
+            modified = modification_times(&current);
+            sender.send_replace(current.clone());
+        }
+    });
+    Ok(receiver)
+}
+
+fn modification_times(config: &Config) -> HashMap<String, Option<SystemTime>> {
+    config
+        .file
+        .iter()
+        .chain(&config.watchlist_file)
+        .map(|path| {
+            let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+            (path.clone(), modified)
+        })
+        .collect()
+}
+
+// Loads and validates the new configuration, returning it with the startup-only
+// settings of `current`, or `None` when it is invalid or nothing live changed
+fn reload(current: &Config) -> Option<Config> {
+    let loaded = match config::load(current.file.as_deref()) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println_time!("Keeping the current configuration, the new one is invalid: {}", e);
+            return None;
+        }
+    };
+    let problems = loaded.monitor_problems();
+    if !problems.is_empty() {
+        println_time!("Keeping the current configuration, the new one cannot run the monitor: {}", problems.join("; "));
+        return None;
+    }
+    merge(current, loaded)
+}
+
+// Applies the live settings of `loaded` on top of `current`, `None` when none changed
+fn merge(current: &Config, loaded: Config) -> Option<Config> {
+    for change in restart_changes(current, &loaded) {
+        println_time!("Configuration change needs a restart to take effect: {}", change);
+    }
+    let mut next = loaded;
+    next.database_path = current.database_path.clone();
+    next.chains = current.chains.clone();
+    next.warnings = current.warnings.clone();
+    next.rpc = current.rpc;
+    next.relay = current.relay;
+    next.monitor.mode = current.monitor.mode;
+    next.monitor.mempool = current.monitor.mempool;
+    next.rescue.enabled = current.rescue.enabled;
+    // Only read by the transaction commands, which load the configuration once
+    next.chain_id = current.chain_id;
+    next.sweep = current.sweep.clone();
+
+    let changes = live_changes(current, &next);
+    if changes.is_empty() {
+        println_time!("Configuration reloaded, nothing to apply");
+        return None;
+    }
+    for change in &changes {
+        println_time!("Configuration change applied: {}", change);
+    }
+    Some(next)
+}
+
+fn restart_changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+    if old.database_path != new.database_path {
+        changes.push(format!("database_path {} -> {}", old.database_path, new.database_path));
+    }
+    if old.rpc != new.rpc {
+        changes.push("rpc settings".to_string());
+    }
+    if old.relay != new.relay {
+        changes.push("relay settings".to_string());
+    }
+    if old.monitor.mode != new.monitor.mode {
+        changes.push(format!("monitor.mode {:?} -> {:?}", old.monitor.mode, new.monitor.mode));
+    }
+    if old.monitor.mempool != new.monitor.mempool {
+        changes.push(format!("monitor.mempool {} -> {}", old.monitor.mempool, new.monitor.mempool));
+    }
+    if old.rescue.enabled != new.rescue.enabled {
+        changes.push(format!("rescue.enabled {} -> {}", old.rescue.enabled, new.rescue.enabled));
+    }
+    if old.chain_id != new.chain_id {
+        changes.push(format!("chain_id {:?} -> {:?}", old.chain_id, new.chain_id));
+    }
+    if old.sweep != new.sweep {
+        changes.push("sweep settings".to_string());
+    }
+    for chain in &new.chains {
+        match old.chains.iter().find(|old| old.chain.chain_id == chain.chain.chain_id) {
+            None => changes.push(format!("chain {} added", chain.chain.name)),
+            Some(old) if old != chain => changes.push(format!("settings of chain {}", chain.chain.name)),
+            Some(_) => {}
+        }
+    }
+    for chain in &old.chains {
+        if !new.chains.iter().any(|new| new.chain.chain_id == chain.chain.chain_id) {
+            changes.push(format!("chain {} removed", chain.chain.name));
+        }
+    }
+    changes
+}
+
+// Human readable changes of the live settings
+fn live_changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+    let mut changed = |name: &str, old: String, new: String| {
+        if old != new {
+            changes.push(format!("{} {} -> {}", name, old, new));
+        }
+    };
+    let (a, b) = (&old.monitor, &new.monitor);
+    changed("monitor.poll_interval", format!("{:?}", a.poll_interval), format!("{:?}", b.poll_interval));
+    changed("monitor.block_interval", format!("{:?}", a.block_interval), format!("{:?}", b.block_interval));
+    changed("monitor.renotify_interval", format!("{:?}", a.renotify_interval), format!("{:?}", b.renotify_interval));
+    changed("monitor.degraded_after_failures", a.degraded_after.to_string(), b.degraded_after.to_string());
+    changed("retry", format!("{:?}", old.retry), format!("{:?}", new.retry));
+    changed("rescue.recipient", format!("{:?}", old.rescue.recipient), format!("{:?}", new.rescue.recipient));
+    changed("rescue.priority_fee", old.rescue.priority_fee.to_string(), new.rescue.priority_fee.to_string());
+    changed("rescue.gas_limit", old.rescue.gas_limit.to_string(), new.rescue.gas_limit.to_string());
+    changed("rescue.bump_after", format!("{:?}", old.rescue.bump_after), format!("{:?}", new.rescue.bump_after));
+
+    let old_entries: HashMap<_, _> = old.watchlist.iter().map(|entry| (entry.address, entry)).collect();
+    let new_entries: HashMap<_, _> = new.watchlist.iter().map(|entry| (entry.address, entry)).collect();
+    for entry in &new.watchlist {
+        match old_entries.get(&entry.address) {
+            None => changes.push(format!("watching {} ({:?})", entry.label, entry.address)),
+            Some(old) if old != &entry => changes.push(format!("updated watched address {} ({:?})", entry.label, entry.address)),
+            Some(_) => {}
+        }
+    }
+    for entry in &old.watchlist {
+        if !new_entries.contains_key(&entry.address) {
+            changes.push(format!("no longer watching {} ({:?})", entry.label, entry.address));
+        }
+    }
+
+    match (&old.telegram, &new.telegram) {
+        (Some(old), Some(new)) => {
+            if old.bot_token != new.bot_token {
+                changes.push("Telegram bot token changed".to_string());
+            }
+            if old.chat_id != new.chat_id {
+                changes.push("Telegram chat id changed".to_string());
+            }
+        }
+        (None, Some(_)) => changes.push("Telegram notifications enabled".to_string()),
+        (Some(_), None) => changes.push("Telegram notifications disabled".to_string()),
+        (None, None) => {}
+    }
+    if old.templates != new.templates {
+        changes.push("alert templates changed".to_string());
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::from_toml;
+
+    const CURRENT: &str = r#"
+        [chains.1]
+        rpc_urls = ["https://mainnet.example.org"]
+
+        [monitor]
+        poll_interval_secs = 60
+
+        [[watchlist.addresses]]
+        address = "0x00000000000000000000000000000000000000aa"
+        label = "treasury"
+        "#;
+
+    fn config(content: &str) -> Config {
+        let (config, problems) = from_toml(content);
+        assert!(problems.is_empty(), "{:?}", problems);
+        config
+    }
+
+    #[test]
+    fn restart_only_changes_are_reported_but_not_applied() {
+        let current = config(CURRENT);
+        let loaded = config(
+            r#"
+            chain_id = 1
+
+            [chains.1]
+            rpc_urls = ["https://mainnet.example.org"]
+
+            [chains.10]
+            rpc_urls = ["https://optimism.example.org"]
+
+            [monitor]
+            poll_interval_secs = 60
+            mempool = true
+
+            [sweep]
+            min_balance = "1"
+
+            [[watchlist.addresses]]
+            address = "0x00000000000000000000000000000000000000aa"
+            label = "treasury"
+            "#,
+        );
+        assert_eq!(
+            restart_changes(&current, &loaded),
+            ["monitor.mempool false -> true", "chain_id None -> Some(1)", "sweep settings", "chain Optimism added"]
+        );
+        assert!(merge(&current, loaded).is_none());
+    }
+
+    #[test]
+    fn watchlist_and_interval_changes_apply_live() {
+        let current = config(CURRENT);
+        let loaded = config(
+            r#"
+            [chains.1]
+            rpc_urls = ["https://mainnet.example.org"]
+
+            [monitor]
+            poll_interval_secs = 30
+            mempool = true
+
+            [sweep]
+            min_balance = "1"
+
+            [[watchlist.addresses]]
+            address = "0x00000000000000000000000000000000000000aa"
+            label = "treasury"
+
+            [[watchlist.addresses]]
+            address = "0x00000000000000000000000000000000000000bb"
+            label = "hot wallet"
+            "#,
+        );
+        assert_eq!(
+            live_changes(&current, &loaded),
+            ["monitor.poll_interval 60s -> 30s", "watching hot wallet (0x00000000000000000000000000000000000000bb)"]
+        );
+
+        let next = merge(&current, loaded).unwrap();
+        assert_eq!(next.monitor.poll_interval, Duration::from_secs(30));
+        assert_eq!(next.watchlist.len(), 2);
+        // Restart-only settings keep their startup values
+        assert!(!next.monitor.mempool);
+        assert_eq!(next.sweep, current.sweep);
+    }
+}
@@ -0,0 +1,586 @@
+use ethers::prelude::*;
+use ethers::utils::{format_ether, format_units};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::client::{ChainClient, SignerClient};
+use crate::contracts::Erc20;
+use crate::config::RescueSettings;
+use crate::error::{Error, Result};
+use crate::freeze::FreezeChecker;
+use crate::relay;
+use crate::retry::RetryPolicy;
+use crate::telegram::TelegramBot;
+use crate::transfer::{self, SweepFees, TokenSweepOutcome};
+
+// Rungs of the pre-signed fee ladder, as multiples of the latest base fee (the gas price
+// on chains without one) and of the priority fee. Each rung raises both fees by more
+// than the 10% nodes require to replace a pending transaction.
+const FEE_LADDER: &[(u64, u64)] = &[(2, 1), (3, 2), (4, 4)];
+
+// How often the receipts of a broadcast ladder are looked up
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Bump intervals the last rung is given before the rescue is reported as unconfirmed
+const LAST_RUNG_PATIENCE: u32 = 10;
+
+// One rung of the ladder, signed and ready for eth_sendRawTransaction
+#[derive(Debug, Clone)]
+struct SignedRescue {
+    tx_hash: TxHash,
+    raw: Bytes,
+    max_gas_price: U256,
+}
+
+// Pre-signed rescue of one address and the chain state it was signed for
+#[derive(Debug, Clone)]
+struct PreparedRescue {
+    nonce: U256,
+    // Token balance, all of which is transferred
+    balance: U256,
+    native_balance: U256,
+    base_fee: U256,
+    // Cheapest first, only the rungs the native balance can pay for
+    ladder: Vec<SignedRescue>,
+}
+
+/// Moves the whole token balance of a watched address to the rescue recipient as soon
+/// as the freeze list of the token clears it, for the addresses whose key it holds.
+///
+/// While an address is frozen the transfer is kept signed at a ladder of fees, so the
+/// rescue starts with a single `eth_sendRawTransaction` and moves up the ladder until
+/// it is mined. Without a usable pre-signed transfer it is built, signed and sent on
+/// the spot. The outcome is reported to Telegram.
+#[derive(Clone)]
+pub struct Rescuer {
+    client: ChainClient,
+    checker: Arc<dyn FreezeChecker>,
+    // Keyed by the address they sign for
+    signers: HashMap<Address, Arc<SignerClient>>,
+    settings: RescueSettings,
+    prepared: HashMap<Address, PreparedRescue>,
+    // Rescues are simulated and reported, but never sent
+    dry_run: bool,
+}
+
+impl Rescuer {
+    pub fn new(
+        client: ChainClient,
+        checker: Arc<dyn FreezeChecker>,
+        signers: &[Arc<SignerClient>],
+        settings: RescueSettings,
+        dry_run: bool,
+    ) -> Self {
+        Rescuer {
+            client,
+            checker,
+            signers: signers.iter().map(|signer| (signer.address(), signer.clone())).collect(),
+            settings,
+            prepared: HashMap::new(),
+            dry_run,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: RescueSettings) {
+        if settings != self.settings {
+            // Signed for the old recipient or fees
+            self.prepared.clear();
+        }
+        self.settings = settings;
+    }
+
+    /// Keeps the pre-signed rescues of the `frozen` addresses in line with their nonce,
+    /// balances and the latest base fee, re-signing whichever changed. Failures only
+    /// leave the previous transfers in place.
+    pub async fn prepare(&mut self, frozen: &[Address]) {
+        self.prepared.retain(|address, _| frozen.contains(address));
+        let frozen: Vec<Address> = frozen.iter().copied().filter(|address| self.signers.contains_key(address)).collect();
+        if frozen.is_empty() || self.settings.recipient.is_none() {
+            return;
+        }
+        let (base_fee, eip1559) = match self.base_fee().await {
+            Ok(base_fee) => base_fee,
+            Err(e) => {
+                println_time!("Failed to fetch the base fee for the pre-signed rescues: {}", e);
+                return;
+            }
+        };
+        for address in frozen {
+            if let Err(e) = self.prepare_one(address, base_fee, eip1559).await {
+                println_time!("Failed to pre-sign the rescue of {:?}: {}", address, e);
+            }
+        }
+    }
+
+    async fn prepare_one(&mut self, address: Address, base_fee: U256, eip1559: bool) -> Result<()> {
+        let signer = &self.signers[&address];
+        let recipient = self.settings.recipient.expect("checked by prepare");
+        let provider = self.client.provider();
+        let nonce = provider.get_transaction_count(address, Some(BlockNumber::Pending.into())).await?;
+        let balance = self.client.erc20(self.checker.token()).balance_of(address).call().await?;
+        let native_balance = provider.get_balance(address, None).await?;
+        let previous = self.prepared.get(&address);
+        if previous.is_some_and(|p| (p.nonce, p.balance, p.native_balance, p.base_fee) == (nonce, balance, native_balance, base_fee)) {
+            return Ok(());
+        }
+        let state_changed = previous.is_none_or(|p| (p.nonce, p.balance, p.native_balance) != (nonce, balance, native_balance));
+
+        let mut ladder = Vec::new();
+        for fees in self.ladder_fees(base_fee, eip1559) {
+            if fees.max_gas_price().saturating_mul(self.settings.gas_limit) > native_balance {
+                break;
+            }
+            let (tx_hash, raw) =
+                transfer::sign_token_transfer(signer, self.checker.token(), recipient, balance, nonce, self.settings.gas_limit, fees).await?;
+            ladder.push(SignedRescue { tx_hash, raw, max_gas_price: fees.max_gas_price() });
+        }
+        if state_changed {
+            println_time!(
+                "Pre-signed the rescue of {} at nonce {} with {} of {} fee rung(s)",
+                self.format(balance),
+                nonce,
+                ladder.len(),
+                FEE_LADDER.len()
+            );
+        }
+        self.prepared.insert(address, PreparedRescue { nonce, balance, native_balance, base_fee, ladder });
+        Ok(())
+    }
+
+    // Base fee of the latest block, or the gas price on chains without one, and which it is
+    async fn base_fee(&self) -> Result<(U256, bool)> {
+        let provider = self.client.provider();
+        let latest = provider
+            .get_block(BlockNumber::Latest)
+            .await?
+            .ok_or_else(|| Error::InvalidResponse("no latest block".to_string()))?;
+        match latest.base_fee_per_gas {
+            Some(base_fee) => Ok((base_fee, true)),
+            None => Ok((provider.get_gas_price().await?, false)),
+        }
+    }
+
+    fn ladder_fees(&self, base_fee: U256, eip1559: bool) -> impl Iterator<Item = SweepFees> + '_ {
+        FEE_LADDER.iter().map(move |&(base_multiple, priority_multiple)| {
+            let priority_fee = self.settings.priority_fee * priority_multiple;
+            let max_fee_per_gas = base_fee * base_multiple + priority_fee;
+            if eip1559 {
+                SweepFees::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas: priority_fee }
+            } else {
+                SweepFees::Legacy { gas_price: max_fee_per_gas }
+            }
+        })
+    }
+
+    fn format(&self, amount: U256) -> String {
+        format!(
+            "{} {}",
+            format_units(amount, self.checker.decimals()).unwrap_or_else(|_| amount.to_string()),
+            self.checker.name()
+        )
+    }
+
+    /// Starts the rescue of `address` in the background, if it is enabled, the key of
+    /// `address` is known and the freeze list clears it at the latest block. The monitor
+    /// keeps running while the transfer confirms. In a dry run the transfer is only
+    /// simulated.
+    pub fn start(&mut self, address: Address, label: String, telegram: TelegramBot, retry: RetryPolicy) {
+        let (true, Some(recipient), Some(signer)) = (self.settings.enabled, self.settings.recipient, self.signers.get(&address)) else {
+            return;
+        };
+        // A dry run skips the pre-signed ladder, whose rungs can only be checked by sending them
+        let prepared = self.prepared.remove(&address).filter(|prepared| !prepared.ladder.is_empty() && !self.dry_run);
+        let (rescuer, signer) = (self.clone(), signer.clone());
+        tokio::spawn(async move {
+            // The removal may come from older logs, with the address frozen again since
+            let frozen = retry
+                .run("Checking the freeze status before the rescue", || async {
+                    Ok(transfer::is_frozen(&rescuer.client, rescuer.checker.as_ref(), address).await?)
+                })
+                .await;
+            let message = match frozen {
+                Ok(true) => {
+                    println_time!("Not rescuing {} ({:?}): {} freezes it again at the latest block", label, address, rescuer.checker.name());
+                    return;
+                }
+                Ok(false) => {
+                    println_time!("Rescuing the {} balance of {} ({:?}) to {:?}", rescuer.checker.name(), label, address, recipient);
+                    match prepared {
+                        Some(prepared) => rescuer.broadcast(&signer, &label, recipient, prepared, retry).await,
+                        None => rescuer.rescue(&signer, &label, recipient, retry).await,
+                    }
+                }
+                Err(e) => format!(
+                    "⚠️ Rescue of {} ({:?}) on {} not sent, its {} freeze status at the latest block is unknown: {:#}",
+                    label,
+                    address,
+                    rescuer.client.chain.name,
+                    rescuer.checker.name(),
+                    e
+                ),
+            };
+            println_time!("{}", message);
+            if let Err(e) = retry.run("Sending the rescue report", || async { Ok(telegram.send_message(&message).await?) }).await {
+                println_time!("Failed to send the rescue report: {:#}", e);
+            }
+        });
+    }
+
+    // Broadcasts the pre-signed ladder one rung at a time until a rung is mined. A ladder
+    // whose transfer fails in a simulation at the latest block, or whose first rung is
+    // rejected without being known to the node, e.g. because the nonce moved on, falls
+    // back to `rescue`.
+    async fn broadcast(
+        &self,
+        signer: &Arc<SignerClient>,
+        label: &str,
+        recipient: Address,
+        prepared: PreparedRescue,
+        retry: RetryPolicy,
+    ) -> String {
+        let amount = self.format(prepared.balance);
+        if let Err(e) = self.simulate(signer.address(), recipient, prepared.balance).await {
+            println_time!("Pre-signed rescue would fail at the latest block, signing a new one: {}", e);
+            return self.rescue(signer, label, recipient, retry).await;
+        }
+        let mut sent: Vec<&SignedRescue> = Vec::new();
+        for rung in &prepared.ladder {
+            match self.send_raw(rung).await {
+                Ok(()) => {
+                    println_time!(
+                        "Pre-signed rescue sent at a max fee of {} gwei: {:?}",
+                        format_units(rung.max_gas_price, "gwei").unwrap_or_default(),
+                        rung.tx_hash
+                    );
+                    sent.push(rung);
+                }
+                // Signing another transfer would double it when this one is pending after all
+                Err(e) if sent.is_empty() && self.known(rung.tx_hash).await => {
+                    println_time!("Pre-signed rescue {:?} is already known to the node: {}", rung.tx_hash, e);
+                    sent.push(rung);
+                }
+                Err(e) if sent.is_empty() => {
+                    println_time!("Pre-signed rescue rejected, signing a new one: {}", e);
+                    return self.rescue(signer, label, recipient, retry).await;
+                }
+                Err(e) => {
+                    println_time!("Fee bump of the rescue rejected: {}", e);
+                    break;
+                }
+            }
+            let hashes: Vec<TxHash> = sent.iter().map(|rung| rung.tx_hash).collect();
+            if let Some(tx_hash) = self.mined(&hashes, self.settings.bump_after).await {
+                return self.confirmed(signer.address(), label, recipient, &amount, tx_hash).await;
+            }
+        }
+
+        // The last rung sent gets more time, in the public mempool if the relays did not include it
+        let last = sent.last().expect("the first rung was sent");
+        if self.client.relay().is_some_and(|relay| relay.settings().fallback_public) {
+            println_time!("Rescue not included through the relays, broadcasting {:?} publicly", last.tx_hash);
+            if let Err(e) = relay::publish(&self.client.provider(), &last.raw, last.tx_hash).await {
+                println_time!("Public broadcast of the rescue failed: {}", e);
+            }
+        }
+        let hashes: Vec<TxHash> = sent.iter().map(|rung| rung.tx_hash).collect();
+        if let Some(tx_hash) = self.mined(&hashes, self.settings.bump_after * LAST_RUNG_PATIENCE).await {
+            return self.confirmed(signer.address(), label, recipient, &amount, tx_hash).await;
+        }
+        format!(
+            "⚠️ Rescue of {} from {} ({:?}) on {} is not confirmed yet (tx {:?})",
+            amount,
+            label,
+            signer.address(),
+            self.client.chain.name,
+            last.tx_hash
+        )
+    }
+
+    // Runs the transfer of `amount` with eth_call at the latest block, failing with its
+    // revert reason, e.g. when the balance moved since it was signed
+    async fn simulate(&self, address: Address, recipient: Address, amount: U256) -> Result<()> {
+        let provider = self.client.provider();
+        // Not decoded, as USDT's transfer returns nothing
+        let mut tx = Erc20::new(self.checker.token(), provider.clone()).transfer(recipient, amount).tx;
+        tx.set_from(address);
+        tx.set_gas(self.settings.gas_limit);
+        provider.call(&tx, None).await?;
+        Ok(())
+    }
+
+    // Whether the node has seen the transaction, pending or mined
+    async fn known(&self, tx_hash: TxHash) -> bool {
+        matches!(self.client.provider().get_transaction(tx_hash).await, Ok(Some(_)))
+    }
+
+    // Through the chain's private relays when it has any, publicly when it has none or
+    // no relay takes the transfer and public broadcasts are allowed
+    async fn send_raw(&self, rung: &SignedRescue) -> Result<()> {
+        let provider = self.client.provider();
+        if let Some(relay) = self.client.relay() {
+            match relay.send(&provider, &rung.raw).await {
+                Ok(_) => return Ok(()),
+                Err(e) if !relay.settings().fallback_public => return Err(e),
+                Err(e) => println_time!("No relay took the rescue, broadcasting it publicly: {}", e),
+            }
+        }
+        relay::publish(&provider, &rung.raw, rung.tx_hash).await
+    }
+
+    // The first of `sent` mined within `patience`
+    async fn mined(&self, sent: &[TxHash], patience: Duration) -> Option<TxHash> {
+        let provider = self.client.provider();
+        let deadline = Instant::now() + patience;
+        loop {
+            for tx_hash in sent {
+                if let Ok(Some(receipt)) = provider.get_transaction_receipt(*tx_hash).await {
+                    if receipt.block_number.is_some() {
+                        return Some(*tx_hash);
+                    }
+                }
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+        }
+    }
+
+    // Signs and sends the transfer on the spot, then waits for it. The gas estimate
+    // simulates the transfer. A failed attempt may still have reached the mempool, so it
+    // is only retried while the pending nonce of the address has not moved.
+    async fn rescue(&self, signer: &Arc<SignerClient>, label: &str, recipient: Address, retry: RetryPolicy) -> String {
+        let chain = &self.client.chain;
+        let token = self.checker.token();
+        let address = signer.address();
+        let provider = self.client.provider();
+        let nonce = match provider.get_transaction_count(address, Some(BlockNumber::Pending.into())).await {
+            Ok(nonce) => nonce,
+            Err(e) => return format!("⚠️ Rescue of {} ({:?}) on {} failed: {}", label, address, chain.name, Error::from(e)),
+        };
+        let retried = AtomicBool::new(false);
+        let sent = retry
+            .run("Sending the rescue transfer", || async {
+                if retried.swap(true, Ordering::SeqCst) {
+                    let pending = provider.get_transaction_count(address, Some(BlockNumber::Pending.into())).await.map_err(Error::from)?;
+                    if pending != nonce {
+                        anyhow::bail!("the nonce of the address moved from {} to {}, an earlier attempt may be pending", nonce, pending);
+                    }
+                }
+                Ok(transfer::rescue_token(&self.client, signer, token, recipient, self.settings.priority_fee, self.dry_run).await?)
+            })
+            .await;
+        let report = match sent {
+            Ok(report) => report,
+            Err(e) => return format!("⚠️ Rescue of {} ({:?}) on {} failed: {:#}", label, address, chain.name, e),
+        };
+        let amount = self.format(report.balance);
+        match report.outcome {
+            TokenSweepOutcome::Sent { tx_hash } => self.confirmed(address, label, recipient, &amount, tx_hash).await,
+            TokenSweepOutcome::Empty => format!("🛟 Nothing to rescue: {} ({:?}) holds no {} on {}", label, address, self.checker.name(), chain.name),
+            TokenSweepOutcome::GasNotCovered { gas_cost } => format!(
+                "⚠️ Cannot rescue {} from {} ({:?}) on {}: it needs up to {} {} for gas",
+                amount,
+                label,
+                address,
+                chain.name,
+                format_ether(gas_cost),
+                chain.native_symbol
+            ),
+            TokenSweepOutcome::DryRun { gas_cost } => format!(
+                "🛟 Dry run: would rescue {} from {} ({:?}) to {:?} on {} for at most {} {} of gas",
+                amount,
+                label,
+                address,
+                recipient,
+                chain.name,
+                format_ether(gas_cost),
+                chain.native_symbol
+            ),
+            // Not produced by a rescue
+            TokenSweepOutcome::Frozen { .. } => {
+                format!("⚠️ Rescue of {} from {} ({:?}) on {} was not sent", amount, label, address, chain.name)
+            }
+        }
+    }
+
+    // Waits for the confirmation depth of a mined or sent rescue
+    async fn confirmed(&self, address: Address, label: &str, recipient: Address, amount: &str, tx_hash: TxHash) -> String {
+        let chain = &self.client.chain;
+        match transfer::wait_for(&self.client, tx_hash, "Rescue").await {
+            Ok(receipt) => format!(
+                "🛟 Rescued {} from {} ({:?}) to {:?} on {} in block {} (tx {:?})",
+                amount,
+                label,
+                address,
+                recipient,
+                chain.name,
+                receipt.block_number.unwrap_or_default(),
+                tx_hash
+            ),
+            Err(e) => format!("⚠️ Rescue of {} from {} ({:?}) on {} failed (tx {:?}): {}", amount, label, address, chain.name, tx_hash, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use crate::freeze::{CheckerSpec, FreezeList, SignatureChecker};
+    use crate::testing::{self, calls, rpc_server, Requests, RpcHandler};
+
+    const TOKEN: Address = Address::repeat_byte(0x70);
+    const RECIPIENT: Address = Address::repeat_byte(0xbb);
+    // balanceOf(address) and transfer(address,uint256)
+    const BALANCE_OF: &str = "0x70a08231";
+    const TRANSFER: &str = "0xa9059cbb";
+
+    // Chain state answered by the mock node
+    struct Node {
+        base_fee: Option<U256>,
+        native_balance: U256,
+        frozen: bool,
+        // Pending nonces answered in turn, the last one from then on
+        nonces: VecDeque<u64>,
+    }
+
+    fn gwei(amount: u64) -> U256 {
+        U256::from(amount) * U256::exp10(9)
+    }
+
+    fn word(value: U256) -> serde_json::Value {
+        json!(format!("0x{:064x}", value))
+    }
+
+    fn settings() -> RescueSettings {
+        RescueSettings {
+            enabled: true,
+            recipient: Some(RECIPIENT),
+            priority_fee: gwei(5),
+            gas_limit: U256::from(100_000),
+            bump_after: Duration::from_secs(1),
+        }
+    }
+
+    fn retry() -> RetryPolicy {
+        RetryPolicy { attempts: 3, base_delay: Duration::ZERO, max_delay: Duration::ZERO }
+    }
+
+    async fn rescuer(node: Node) -> (Rescuer, Arc<SignerClient>, Requests) {
+        let node = Mutex::new(node);
+        let handler: Arc<RpcHandler> = Arc::new(move |method, params| {
+            let mut node = node.lock().unwrap();
+            match method {
+                "eth_getBlockByNumber" => {
+                    Ok(json!({ "number": "0x10", "hash": H256::repeat_byte(1), "baseFeePerGas": node.base_fee }))
+                }
+                "eth_gasPrice" => Ok(word(gwei(10))),
+                "eth_getBalance" => Ok(word(node.native_balance)),
+                "eth_getTransactionCount" => {
+                    let nonce = if node.nonces.len() > 1 { node.nonces.pop_front() } else { node.nonces.front().copied() };
+                    Ok(word(U256::from(nonce.unwrap_or_default())))
+                }
+                "eth_call" => {
+                    let data = params[0]["data"].as_str().or(params[0]["input"].as_str()).unwrap_or_default();
+                    if data.starts_with(BALANCE_OF) {
+                        Ok(word(U256::from(100)))
+                    } else if data.starts_with(TRANSFER) {
+                        Ok(json!("0x"))
+                    } else {
+                        Ok(word(U256::from(node.frozen as u8)))
+                    }
+                }
+                "eth_estimateGas" => Err(json!({ "code": -32000, "message": "header not found" })),
+                _ => Err(json!({ "code": -32601, "message": "method not found" })),
+            }
+        });
+        let (url, received) = rpc_server(handler).await;
+        let client = testing::client(&url, 42161);
+        let signer = client.signer(&format!("{:064x}", 1)).unwrap();
+        let spec = CheckerSpec::builtin("USDT", FreezeList::TetherV2, TOKEN);
+        let checker = Arc::new(SignatureChecker::new(spec, client.chain.clone()).unwrap());
+        let rescuer = Rescuer::new(client, checker, std::slice::from_ref(&signer), settings(), false);
+        (rescuer, signer, received)
+    }
+
+    fn node() -> Node {
+        Node { base_fee: Some(gwei(10)), native_balance: U256::exp10(18), frozen: false, nonces: VecDeque::from([5]) }
+    }
+
+    #[tokio::test]
+    async fn ladder_rungs_raise_both_fees() {
+        let (rescuer, _, _) = rescuer(node()).await;
+        let fees: Vec<SweepFees> = rescuer.ladder_fees(gwei(10), true).collect();
+        assert_eq!(
+            fees,
+            [
+                SweepFees::Eip1559 { max_fee_per_gas: gwei(25), max_priority_fee_per_gas: gwei(5) },
+                SweepFees::Eip1559 { max_fee_per_gas: gwei(40), max_priority_fee_per_gas: gwei(10) },
+                SweepFees::Eip1559 { max_fee_per_gas: gwei(60), max_priority_fee_per_gas: gwei(20) },
+            ]
+        );
+        // Every rung can replace the one before it
+        for pair in fees.windows(2) {
+            assert!(pair[1].max_gas_price() * 10 > pair[0].max_gas_price() * 11);
+        }
+        let legacy: Vec<SweepFees> = rescuer.ladder_fees(gwei(10), false).collect();
+        assert_eq!(legacy[0], SweepFees::Legacy { gas_price: gwei(25) });
+    }
+
+    #[tokio::test]
+    async fn only_the_rungs_the_native_balance_pays_for_are_signed() {
+        // 100k gas at 40 gwei, short of the third rung at 60 gwei
+        let (mut rescuer, signer, _) = rescuer(Node { native_balance: gwei(4_000_000), ..node() }).await;
+        rescuer.prepare(&[signer.address()]).await;
+        let prepared = &rescuer.prepared[&signer.address()];
+        assert_eq!(prepared.nonce, U256::from(5));
+        assert_eq!(prepared.balance, U256::from(100));
+        let fees: Vec<U256> = prepared.ladder.iter().map(|rung| rung.max_gas_price).collect();
+        assert_eq!(fees, [gwei(25), gwei(40)]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_attempt_is_not_retried_once_the_nonce_moved() {
+        // The first attempt fails after it may have reached the mempool
+        let (rescuer, signer, received) = rescuer(Node { nonces: VecDeque::from([5, 6]), ..node() }).await;
+        let message = rescuer.rescue(&signer, "treasury", RECIPIENT, retry()).await;
+        assert!(message.contains("the nonce of the address moved from 5 to 6"), "{}", message);
+        assert_eq!(calls(&received, "eth_estimateGas").len(), 1);
+        assert!(calls(&received, "eth_sendRawTransaction").is_empty());
+    }
+
+    #[tokio::test]
+    async fn nothing_is_broadcast_while_the_address_is_frozen_at_the_latest_block() {
+        let (mut rescuer, signer, received) = rescuer(Node { frozen: true, ..node() }).await;
+        rescuer.prepare(&[signer.address()]).await;
+        assert_eq!(rescuer.prepared[&signer.address()].ladder.len(), FEE_LADDER.len());
+
+        let (telegram, messages) = testing::telegram(200).await;
+        rescuer.start(signer.address(), "treasury".to_string(), telegram, retry());
+        // Wait for the freeze check of the rescue, on top of the balance read of `prepare`
+        let freeze_checks = || {
+            calls(&received, "eth_call")
+                .iter()
+                .filter(|call| !call.body.contains(BALANCE_OF.trim_start_matches("0x")))
+                .count()
+        };
+        for _ in 0..100 {
+            if freeze_checks() > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(freeze_checks(), 1);
+        assert!(calls(&received, "eth_sendRawTransaction").is_empty());
+        assert!(calls(&received, "eth_estimateGas").is_empty());
+        assert!(testing::messages(&messages).is_empty());
+    }
+}
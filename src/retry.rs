@@ -0,0 +1,289 @@
+use ethers::providers::{JsonRpcError, ProviderError, WsClientError};
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+use crate::error::Error;
+
+// Whether an error is worth retrying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    // Timeouts, unreachable or rate limited endpoints, busy database
+    Transient,
+    // Reverts, rejected requests and broken local state; retrying gives the same answer
+    Fatal,
+}
+
+/// Classifies an error by the first recognised error type in its chain.
+/// Errors of unknown types are treated as fatal.
+pub fn classify(error: &anyhow::Error) -> ErrorClass {
+    for cause in error.chain() {
+        if let Some(error) = cause.downcast_ref::<Error>() {
+            return error.class();
+        }
+        if let Some(error) = cause.downcast_ref::<ProviderError>() {
+            return classify_provider(error);
+        }
+        if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+            return classify_http(error);
+        }
+        if let Some(error) = cause.downcast_ref::<rusqlite::Error>() {
+            return match error.sqlite_error_code() {
+                Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => ErrorClass::Transient,
+                _ => ErrorClass::Fatal,
+            };
+        }
+        if cause.is::<WsClientError>() || cause.is::<std::io::Error>() || cause.is::<tokio::time::error::Elapsed>() {
+            return ErrorClass::Transient;
+        }
+    }
+    ErrorClass::Fatal
+}
+
+pub fn classify_provider(error: &ProviderError) -> ErrorClass {
+    match error {
+        ProviderError::JsonRpcClientError(error) => match error.as_error_response() {
+            Some(response) => classify_response(response),
+            // No answer from the node: transport failure, timeout or missing quorum
+            None => ErrorClass::Transient,
+        },
+        ProviderError::HTTPError(error) => classify_http(error),
+        ProviderError::SerdeJson(_) => ErrorClass::Transient,
+        _ => ErrorClass::Fatal,
+    }
+}
+
+// The node answered, so only errors about its own state are worth retrying;
+// nonce conflicts are, since the transaction is rebuilt with a fresh nonce
+fn classify_response(response: &JsonRpcError) -> ErrorClass {
+    let message = response.message.to_lowercase();
+    if response.code == 3 || ["revert", "insufficient funds", "invalid"].iter().any(|m| message.contains(m)) {
+        ErrorClass::Fatal
+    } else {
+        ErrorClass::Transient
+    }
+}
+
+pub fn classify_http(error: &reqwest::Error) -> ErrorClass {
+    match error.status() {
+        Some(status) if status.as_u16() == 429 || status.is_server_error() => ErrorClass::Transient,
+        Some(_) => ErrorClass::Fatal,
+        None if error.is_builder() => ErrorClass::Fatal,
+        None => ErrorClass::Transient,
+    }
+}
+
+// Delay bounds between WebSocket reconnection attempts
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Backoff between the reconnection attempts of a WebSocket subscription, doubling from
+/// one second to a minute and starting over once a connection is established.
+#[derive(Debug, Clone, Copy)]
+pub struct Reconnect {
+    delay: Duration,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Reconnect { delay: MIN_RECONNECT_DELAY }
+    }
+}
+
+impl Reconnect {
+    /// Called once the subscription is up again.
+    pub fn connected(&mut self) {
+        self.delay = MIN_RECONNECT_DELAY;
+    }
+
+    /// Waits before the next attempt.
+    pub async fn wait(&mut self) {
+        println_time!("Reconnecting to the WebSocket provider in {:?}", self.delay);
+        tokio::time::sleep(self.delay).await;
+        self.delay = (self.delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+/// Failures in a row of a loop that alerts once when it degrades, after `degraded_after`
+/// failures in a row or a fatal error, and once when a run succeeds again.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Health {
+    consecutive_failures: u32,
+    degraded: bool,
+}
+
+impl Health {
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Records a successful run, returning the failures in a row it recovers from when it
+    /// ends a degraded spell.
+    pub fn succeeded(&mut self) -> Option<u32> {
+        let failures = std::mem::take(&mut self.consecutive_failures);
+        std::mem::take(&mut self.degraded).then_some(failures)
+    }
+
+    /// Records a failed run, returning whether the loop just became degraded.
+    pub fn failed(&mut self, class: ErrorClass, degraded_after: u32) -> bool {
+        self.consecutive_failures += 1;
+        if self.degraded || (class != ErrorClass::Fatal && self.consecutive_failures < degraded_after) {
+            return false;
+        }
+        self.degraded = true;
+        true
+    }
+}
+
+/// Retry policy for single operations: exponential backoff with full jitter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at 0), picked uniformly up to the
+    /// exponential bound so that monitors failing together do not retry in lockstep.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let bound = self.base_delay.saturating_mul(1 << retry.min(16)).min(self.max_delay);
+        bound.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    /// Runs `operation` until it succeeds, fails with a fatal error or runs out of attempts.
+    pub async fn run<T, F, Fut>(&self, what: &str, mut operation: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut retry = 0;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if retry + 1 < self.attempts && classify(&e) == ErrorClass::Transient => {
+                    let delay = self.delay(retry);
+                    println_time!("{} failed: {:#}, retrying in {:?}", what, e, delay);
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use ethers::providers::HttpClientError;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn response(code: i64, message: &str) -> ProviderError {
+        let error = JsonRpcError { code, message: message.to_string(), data: None };
+        ProviderError::JsonRpcClientError(Box::new(HttpClientError::JsonRpcError(error)))
+    }
+
+    fn policy(attempts: u32) -> RetryPolicy {
+        RetryPolicy { attempts, base_delay: Duration::ZERO, max_delay: Duration::ZERO }
+    }
+
+    #[test]
+    fn classifies_node_answers() {
+        assert_eq!(classify_provider(&response(-32000, "header not found")), ErrorClass::Transient);
+        assert_eq!(classify_provider(&response(-32005, "rate limit exceeded")), ErrorClass::Transient);
+        assert_eq!(classify_provider(&response(3, "execution reverted")), ErrorClass::Fatal);
+        assert_eq!(classify_provider(&response(-32000, "insufficient funds for gas * price + value")), ErrorClass::Fatal);
+        assert_eq!(classify_provider(&response(-32602, "invalid argument 0")), ErrorClass::Fatal);
+    }
+
+    #[test]
+    fn classifies_by_the_first_known_error_in_the_chain() {
+        let transient = anyhow::Error::new(Error::Nonce("nonce too low".to_string())).context("Sending the sweep");
+        assert_eq!(classify(&transient), ErrorClass::Transient);
+        let fatal = anyhow::Error::new(Error::Revert { reason: "blacklisted".to_string() }).context("Sending the sweep");
+        assert_eq!(classify(&fatal), ErrorClass::Fatal);
+
+        // Node answers about the transaction itself become crate errors first
+        assert!(matches!(Error::from(response(-32000, "nonce too low")), Error::Nonce(_)));
+        assert_eq!(classify(&Error::from(response(-32000, "nonce too low")).into()), ErrorClass::Transient);
+
+        let io: anyhow::Result<()> = Err(std::io::Error::from(std::io::ErrorKind::ConnectionReset).into());
+        assert_eq!(classify(&io.context("Reading").unwrap_err()), ErrorClass::Transient);
+    }
+
+    #[test]
+    fn classifies_database_errors() {
+        let busy = rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None);
+        assert_eq!(classify(&busy.into()), ErrorClass::Transient);
+        assert_eq!(classify(&rusqlite::Error::QueryReturnedNoRows.into()), ErrorClass::Fatal);
+    }
+
+    #[test]
+    fn unknown_errors_are_fatal() {
+        assert_eq!(classify(&anyhow::anyhow!("something else")), ErrorClass::Fatal);
+    }
+
+    #[test]
+    fn health_alerts_once_per_degraded_spell() {
+        let mut health = Health::default();
+        assert!(!health.failed(ErrorClass::Transient, 3));
+        assert!(!health.failed(ErrorClass::Transient, 3));
+        assert!(health.failed(ErrorClass::Transient, 3));
+        assert!(!health.failed(ErrorClass::Fatal, 3));
+        assert_eq!(health.succeeded(), Some(4));
+        assert_eq!(health.succeeded(), None);
+
+        assert!(health.failed(ErrorClass::Fatal, 3));
+        assert_eq!(health.consecutive_failures(), 1);
+        assert_eq!(health.succeeded(), Some(1));
+        assert_eq!(health.consecutive_failures(), 0);
+    }
+
+    #[test]
+    fn delays_stay_below_the_exponential_bound() {
+        let policy = RetryPolicy { attempts: 4, base_delay: Duration::from_millis(100), max_delay: Duration::from_secs(1) };
+        for retry in 0..20 {
+            let bound = Duration::from_millis(100 << retry.min(4)).min(Duration::from_secs(1));
+            assert!(policy.delay(retry) <= bound);
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors_until_the_attempts_run_out() {
+        let calls = AtomicU32::new(0);
+        let result: anyhow::Result<()> = policy(3)
+            .run("Test", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(Error::BlockNotFound("latest".to_string()).into())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        let result = policy(3)
+            .run("Test", || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(Error::BlockNotFound("latest".to_string()).into()),
+                    n => Ok(n),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_fatal_errors() {
+        let calls = AtomicU32::new(0);
+        let result: anyhow::Result<()> = policy(3)
+            .run("Test", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(Error::Config("bad".to_string()).into())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,345 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use ethers::providers::{Http, HttpClientError, JsonRpcClient, JsonRpcError, ProviderError, RpcError};
+use ethers::types::U64;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+use crate::chains::ChainInfo;
+
+// Reads that decide whether an alert is sent; these must be confirmed by the quorum
+const QUORUM_METHODS: &[&str] = &["eth_call", "eth_getLogs"];
+
+// How long a failing endpoint is skipped, doubling with every consecutive failure
+const MIN_COOLDOWN: Duration = Duration::from_secs(5);
+const MAX_COOLDOWN: Duration = Duration::from_secs(300);
+
+// Endpoints whose head is further behind the best known head are only used as a last resort
+const MAX_HEAD_LAG: u64 = 10;
+
+#[derive(Debug, Default)]
+struct Health {
+    consecutive_failures: u32,
+    retry_at: Option<Instant>,
+    head: Option<u64>,
+    lagging: bool,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    name: String,
+    http: Http,
+    health: Mutex<Health>,
+}
+
+impl Endpoint {
+    fn health(&self) -> std::sync::MutexGuard<'_, Health> {
+        self.health.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn usable(&self) -> bool {
+        let health = self.health();
+        !health.lagging && health.retry_at.is_none_or(|retry_at| Instant::now() >= retry_at)
+    }
+}
+
+#[derive(Debug)]
+struct PoolInner {
+    chain: String,
+    endpoints: Vec<Endpoint>,
+    quorum: usize,
+    timeout: Duration,
+}
+
+/// Parses an RPC, WebSocket or relay URL, along with the name it is shown under: its host
+/// and port. The path and query often embed an API key, so the rest of the URL is never
+/// shown, and neither is the reason it failed to parse.
+pub fn parse_url(url: &str) -> Option<(reqwest::Url, String)> {
+    let parsed: reqwest::Url = url.parse().ok()?;
+    let name = match (parsed.host_str()?, parsed.port()) {
+        (host, Some(port)) => format!("{}:{}", host, port),
+        (host, None) => host.to_string(),
+    };
+    Some((parsed, name))
+}
+
+/// JSON-RPC transport spreading requests over every RPC endpoint of a chain.
+///
+/// Requests go to the first healthy endpoint and fail over to the next one on transport
+/// errors and timeouts. Failing endpoints are skipped for a growing cooldown and
+/// periodic health checks take endpoints that fall behind the chain head out of rotation.
+///
+/// With a quorum above 1, blacklist reads (`eth_call`, `eth_getLogs`) are sent to every
+/// usable endpoint and only succeed when at least `quorum` of them return the same result.
+#[derive(Debug, Clone)]
+pub struct ProviderPool {
+    inner: Arc<PoolInner>,
+}
+
+impl ProviderPool {
+    pub fn new(chain: &ChainInfo, timeout: Duration) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("Failed to build HTTP client")?;
+        let mut endpoints = Vec::new();
+        for (index, url) in chain.rpc_urls.iter().enumerate() {
+            let (parsed, name) =
+                parse_url(url).ok_or_else(|| anyhow::anyhow!("Invalid RPC URL #{} for {}", index + 1, chain.name))?;
+            endpoints.push(Endpoint {
+                name,
+                http: Http::new_with_client(parsed, client.clone()),
+                health: Mutex::new(Health::default()),
+            });
+        }
+        Ok(ProviderPool {
+            inner: Arc::new(PoolInner {
+                chain: chain.name.clone(),
+                endpoints,
+                quorum: chain.quorum,
+                timeout,
+            }),
+        })
+    }
+
+    // Usable endpoints first, in configured order, then the rest as a last resort
+    fn ordered(&self) -> Vec<usize> {
+        let (mut usable, rest): (Vec<usize>, Vec<usize>) =
+            (0..self.inner.endpoints.len()).partition(|&index| self.inner.endpoints[index].usable());
+        usable.extend(rest);
+        usable
+    }
+
+    fn record_success(&self, index: usize) {
+        let endpoint = &self.inner.endpoints[index];
+        let mut health = endpoint.health();
+        if health.consecutive_failures > 0 {
+            println_time!("{} RPC {} recovered", self.inner.chain, endpoint.name);
+        }
+        health.consecutive_failures = 0;
+        health.retry_at = None;
+    }
+
+    fn record_failure(&self, index: usize, error: &str) {
+        let endpoint = &self.inner.endpoints[index];
+        let mut health = endpoint.health();
+        health.consecutive_failures += 1;
+        let cooldown = MIN_COOLDOWN
+            .saturating_mul(1 << (health.consecutive_failures - 1).min(16))
+            .min(MAX_COOLDOWN);
+        health.retry_at = Some(Instant::now() + cooldown);
+        println_time!(
+            "{} RPC {} failed ({} in a row): {}, skipping it for {:?}",
+            self.inner.chain,
+            endpoint.name,
+            health.consecutive_failures,
+            error,
+            cooldown
+        );
+    }
+
+    async fn send(&self, index: usize, method: &str, params: &Value) -> Result<Value, Attempt> {
+        let endpoint = &self.inner.endpoints[index];
+        match tokio::time::timeout(self.inner.timeout, endpoint.http.request::<_, Value>(method, params)).await {
+            Ok(Ok(value)) => {
+                self.record_success(index);
+                Ok(value)
+            }
+            // The endpoint is up and answered, any other endpoint would answer the same
+            Ok(Err(HttpClientError::JsonRpcError(error))) => {
+                self.record_success(index);
+                Err(Attempt::Answered(HttpClientError::JsonRpcError(error)))
+            }
+            Ok(Err(error)) => {
+                let message = match error {
+                    HttpClientError::ReqwestError(error) => error.without_url().to_string(),
+                    error => error.to_string(),
+                };
+                self.record_failure(index, &message);
+                Err(Attempt::Failed(format!("{}: {}", endpoint.name, message)))
+            }
+            Err(_) => {
+                let message = format!("timed out after {:?}", self.inner.timeout);
+                self.record_failure(index, &message);
+                Err(Attempt::Failed(format!("{}: {}", endpoint.name, message)))
+            }
+        }
+    }
+
+    async fn failover(&self, method: &str, params: &Value) -> Result<Value, PoolError> {
+        let mut failures = Vec::new();
+        for index in self.ordered() {
+            match self.send(index, method, params).await {
+                Ok(value) => return Ok(value),
+                Err(Attempt::Answered(error)) => return Err(PoolError::Response(error)),
+                Err(Attempt::Failed(failure)) => failures.push(failure),
+            }
+        }
+        Err(PoolError::Unavailable(failures))
+    }
+
+    async fn quorum_request(&self, method: &str, params: &Value) -> Result<Value, PoolError> {
+        let mut targets: Vec<usize> = self.ordered().into_iter().filter(|&i| self.inner.endpoints[i].usable()).collect();
+        if targets.len() < self.inner.quorum {
+            targets = self.ordered();
+        }
+
+        let mut requests = JoinSet::new();
+        for index in targets {
+            let pool = self.clone();
+            let method = method.to_string();
+            let params = params.clone();
+            requests.spawn(async move { (index, pool.send(index, &method, &params).await) });
+        }
+
+        // Group identical answers and return as soon as one group reaches the quorum
+        let mut answers: Vec<(Value, usize)> = Vec::new();
+        let mut disagreements = Vec::new();
+        while let Some(joined) = requests.join_next().await {
+            let Ok((index, result)) = joined else { continue };
+            let name = &self.inner.endpoints[index].name;
+            match result {
+                Ok(value) => {
+                    let group = match answers.iter().position(|(answer, _)| *answer == value) {
+                        Some(group) => {
+                            answers[group].1 += 1;
+                            group
+                        }
+                        None => {
+                            answers.push((value, 1));
+                            answers.len() - 1
+                        }
+                    };
+                    if answers[group].1 >= self.inner.quorum {
+                        return Ok(answers.swap_remove(group).0);
+                    }
+                    disagreements.push(format!("{}: result #{}", name, group + 1));
+                }
+                Err(Attempt::Answered(error)) => disagreements.push(format!("{}: {}", name, error)),
+                Err(Attempt::Failed(failure)) => disagreements.push(failure),
+            }
+        }
+        Err(PoolError::QuorumNotReached {
+            quorum: self.inner.quorum,
+            responses: disagreements,
+        })
+    }
+
+    /// Probes every endpoint with `eth_blockNumber`, recording failures and taking
+    /// endpoints more than `MAX_HEAD_LAG` blocks behind the best head out of rotation.
+    pub async fn check_health(&self) {
+        for index in 0..self.inner.endpoints.len() {
+            let head = match self.send(index, "eth_blockNumber", &Value::Array(Vec::new())).await {
+                Ok(value) => serde_json::from_value::<U64>(value).ok().map(|head| head.as_u64()),
+                Err(_) => None,
+            };
+            self.inner.endpoints[index].health().head = head;
+        }
+
+        let best = self.inner.endpoints.iter().filter_map(|endpoint| endpoint.health().head).max();
+        for endpoint in &self.inner.endpoints {
+            let mut health = endpoint.health();
+            let lagging = match (health.head, best) {
+                (Some(head), Some(best)) => head + MAX_HEAD_LAG < best,
+                _ => false,
+            };
+            if lagging != health.lagging {
+                match lagging {
+                    true => println_time!("{} RPC {} is lagging at block {:?}, best is {:?}", self.inner.chain, endpoint.name, health.head, best),
+                    false => println_time!("{} RPC {} caught up with the chain head", self.inner.chain, endpoint.name),
+                }
+            }
+            health.lagging = lagging;
+        }
+    }
+
+    /// Runs `check_health` every `interval` in the background.
+    pub fn spawn_health_checks(&self, interval: Duration) {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                pool.check_health().await;
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for ProviderPool {
+    type Error = PoolError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params).map_err(PoolError::Serde)?;
+        let value = if self.inner.quorum > 1 && QUORUM_METHODS.contains(&method) {
+            self.quorum_request(method, &params).await?
+        } else {
+            self.failover(method, &params).await?
+        };
+        serde_json::from_value(value).map_err(PoolError::Serde)
+    }
+}
+
+// Outcome of a single request to one endpoint
+enum Attempt {
+    Answered(HttpClientError),
+    Failed(String),
+}
+
+#[derive(Debug)]
+pub enum PoolError {
+    // An endpoint answered with a JSON-RPC error, e.g. a revert or a too large log range
+    Response(HttpClientError),
+    // Every endpoint failed or timed out
+    Unavailable(Vec<String>),
+    // Fewer than `quorum` endpoints returned the same result
+    QuorumNotReached { quorum: usize, responses: Vec<String> },
+    Serde(serde_json::Error),
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolError::Response(error) => write!(f, "{}", error),
+            PoolError::Unavailable(failures) => write!(f, "all RPC endpoints failed: {}", failures.join("; ")),
+            PoolError::QuorumNotReached { quorum, responses } => {
+                write!(f, "no {} RPC endpoints agreed: {}", quorum, responses.join("; "))
+            }
+            PoolError::Serde(error) => write!(f, "invalid JSON-RPC payload: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {}
+
+impl RpcError for PoolError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            PoolError::Response(error) => error.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            PoolError::Response(error) => error.as_serde_error(),
+            PoolError::Serde(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<PoolError> for ProviderError {
+    fn from(error: PoolError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(error))
+    }
+}
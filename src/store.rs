@@ -0,0 +1,379 @@
+use anyhow::{anyhow, Context};
+use ethers::types::{Address, H256, U256};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::blacklist_events::{BlacklistChange, BlacklistEvent};
+
+// Meta key holding the highest block whose freeze logs of a token on a chain are all stored
+const SYNCED_BLOCK_KEY: &str = "synced_block";
+// Meta key holding the last block the live monitor of a token on a chain fully processed
+const LAST_CHECKED_BLOCK_KEY: &str = "last_checked_block";
+
+// Bumped whenever the schema changes in a way that needs a migration
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS blacklist_events (
+    chain_id     INTEGER NOT NULL,
+    token        TEXT    NOT NULL,
+    address      TEXT    NOT NULL,
+    kind         TEXT    NOT NULL,
+    amount       TEXT,
+    block_number INTEGER NOT NULL,
+    tx_hash      TEXT    NOT NULL,
+    log_index    INTEGER NOT NULL,
+    PRIMARY KEY (chain_id, tx_hash, log_index)
+);
+CREATE INDEX IF NOT EXISTS blacklist_events_chain_token_address ON blacklist_events (chain_id, token, address, block_number);
+CREATE TABLE IF NOT EXISTS meta (
+    key   TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS address_status (
+    chain_id     INTEGER NOT NULL,
+    token        TEXT    NOT NULL,
+    address      TEXT    NOT NULL,
+    blacklisted  INTEGER NOT NULL,
+    block_number INTEGER NOT NULL,
+    updated_at   TEXT    NOT NULL,
+    PRIMARY KEY (chain_id, token, address)
+);
+CREATE TABLE IF NOT EXISTS sent_alerts (
+    chain_id     INTEGER NOT NULL,
+    token        TEXT    NOT NULL,
+    address      TEXT    NOT NULL,
+    kind         TEXT    NOT NULL,
+    block_number INTEGER NOT NULL,
+    sent_at      TEXT    NOT NULL,
+    PRIMARY KEY (chain_id, token, address, kind, block_number)
+);
+"#;
+
+// Local SQLite database with the freeze timeline of every address seen, per chain and token
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    /// Opens (or creates) the database at `path` and makes sure the schema exists.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open database {}", path.display()))?;
+        Self::with_connection(conn)
+    }
+
+    /// Empty database that lives as long as the store.
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self::with_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn with_connection(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(SCHEMA).context("Failed to create database schema")?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Store { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Stores a freeze list event, returning `false` if it was already known.
+    pub fn record_event(&self, event: &BlacklistEvent) -> anyhow::Result<bool> {
+        let (kind, amount) = match event.change {
+            BlacklistChange::Added => ("added", None),
+            BlacklistChange::Removed => ("removed", None),
+            BlacklistChange::Destroyed(amount) => ("destroyed", Some(amount.to_string())),
+        };
+        let inserted = self.conn().execute(
+            "INSERT OR IGNORE INTO blacklist_events (chain_id, token, address, kind, amount, block_number, tx_hash, log_index)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                event.chain_id,
+                format!("{:?}", event.token),
+                format!("{:?}", event.address),
+                kind,
+                amount,
+                event.block_number,
+                format!("{:?}", event.tx_hash),
+                event.log_index.as_u64(),
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Deletes the events logged by a transaction that was reorged out of `chain_id`.
+    pub fn forget_events(&self, chain_id: u64, tx_hash: H256) -> anyhow::Result<()> {
+        self.conn().execute(
+            "DELETE FROM blacklist_events WHERE chain_id = ?1 AND tx_hash = ?2",
+            params![chain_id, format!("{:?}", tx_hash)],
+        )?;
+        Ok(())
+    }
+
+    /// Returns every stored event for `address` across all chains and tokens, ordered by
+    /// chain and then oldest first.
+    pub fn timeline(&self, address: Address) -> anyhow::Result<Vec<BlacklistEvent>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT chain_id, token, kind, amount, block_number, tx_hash, log_index FROM blacklist_events
+             WHERE address = ?1 ORDER BY chain_id, block_number, log_index",
+        )?;
+        let rows = stmt.query_map(params![format!("{:?}", address)], |row| {
+            Ok((
+                row.get::<_, u64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, u64>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, u64>(6)?,
+            ))
+        })?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let (chain_id, token, kind, amount, block_number, tx_hash, log_index) = row?;
+            let change = match kind.as_str() {
+                "added" => BlacklistChange::Added,
+                "removed" => BlacklistChange::Removed,
+                "destroyed" => BlacklistChange::Destroyed(U256::from_dec_str(&amount.unwrap_or_default())?),
+                other => return Err(anyhow!("Unknown event kind {} in database", other)),
+            };
+            events.push(BlacklistEvent {
+                chain_id,
+                token: token.parse::<Address>()?,
+                address,
+                change,
+                block_number,
+                block_hash: None,
+                tx_hash: tx_hash.parse::<H256>()?,
+                log_index: log_index.into(),
+            });
+        }
+        Ok(events)
+    }
+
+    fn meta_block(&self, key: &str, chain_id: u64, token: Address) -> anyhow::Result<Option<u64>> {
+        let value: Option<String> = self
+            .conn()
+            .query_row("SELECT value FROM meta WHERE key = ?1", params![meta_key(key, chain_id, token)], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(value.map(|value| value.parse()).transpose()?)
+    }
+
+    fn set_meta_block(&self, key: &str, chain_id: u64, token: Address, block: u64) -> anyhow::Result<()> {
+        self.conn().execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![meta_key(key, chain_id, token), block.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Highest block up to which all freeze logs of `token` on `chain_id` have been stored.
+    pub fn synced_block(&self, chain_id: u64, token: Address) -> anyhow::Result<Option<u64>> {
+        self.meta_block(SYNCED_BLOCK_KEY, chain_id, token)
+    }
+
+    /// Records that every freeze log of `token` on `chain_id` up to and including `block` is stored.
+    pub fn set_synced_block(&self, chain_id: u64, token: Address, block: u64) -> anyhow::Result<()> {
+        self.set_meta_block(SYNCED_BLOCK_KEY, chain_id, token, block)
+    }
+
+    /// Marks blocks `from..=to` as stored by the live monitor. The synced block only moves
+    /// forward when the range is contiguous with an earlier backfill, otherwise the gap is
+    /// left for the next backfill to fill.
+    pub fn extend_synced(&self, chain_id: u64, token: Address, from: u64, to: u64) -> anyhow::Result<()> {
+        match self.synced_block(chain_id, token)? {
+            Some(synced) if from <= synced + 1 && to > synced => self.set_synced_block(chain_id, token, to),
+            _ => Ok(()),
+        }
+    }
+
+    /// Last block the live monitor of `token` on `chain_id` fully processed before it stopped.
+    pub fn last_checked_block(&self, chain_id: u64, token: Address) -> anyhow::Result<Option<u64>> {
+        self.meta_block(LAST_CHECKED_BLOCK_KEY, chain_id, token)
+    }
+
+    pub fn set_last_checked_block(&self, chain_id: u64, token: Address, block: u64) -> anyhow::Result<()> {
+        self.set_meta_block(LAST_CHECKED_BLOCK_KEY, chain_id, token, block)
+    }
+
+    /// Loads the last known freeze status of every address for `token` on `chain_id`.
+    pub fn load_statuses(&self, chain_id: u64, token: Address) -> anyhow::Result<HashMap<Address, bool>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT address, blacklisted FROM address_status WHERE chain_id = ?1 AND token = ?2")?;
+        let rows = stmt.query_map(params![chain_id, format!("{:?}", token)], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
+        })?;
+        let mut statuses = HashMap::new();
+        for row in rows {
+            let (address, blacklisted) = row?;
+            statuses.insert(address.parse::<Address>()?, blacklisted);
+        }
+        Ok(statuses)
+    }
+
+    pub fn save_status(
+        &self,
+        chain_id: u64,
+        token: Address,
+        address: Address,
+        blacklisted: bool,
+        block: u64,
+    ) -> anyhow::Result<()> {
+        self.conn().execute(
+            "INSERT INTO address_status (chain_id, token, address, blacklisted, block_number, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(chain_id, token, address) DO UPDATE SET
+                 blacklisted = excluded.blacklisted,
+                 block_number = excluded.block_number,
+                 updated_at = excluded.updated_at",
+            params![
+                chain_id,
+                format!("{:?}", token),
+                format!("{:?}", address),
+                blacklisted,
+                block,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Whether an alert of `kind` was already sent for `address` on `token` at `block` of `chain_id`.
+    pub fn alert_sent(&self, chain_id: u64, token: Address, address: Address, kind: &str, block: u64) -> anyhow::Result<bool> {
+        let count: u64 = self.conn().query_row(
+            "SELECT COUNT(*) FROM sent_alerts
+             WHERE chain_id = ?1 AND token = ?2 AND address = ?3 AND kind = ?4 AND block_number = ?5",
+            params![chain_id, format!("{:?}", token), format!("{:?}", address), kind, block],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    pub fn record_alert(&self, chain_id: u64, token: Address, address: Address, kind: &str, block: u64) -> anyhow::Result<()> {
+        self.conn().execute(
+            "INSERT OR IGNORE INTO sent_alerts (chain_id, token, address, kind, block_number, sent_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                chain_id,
+                format!("{:?}", token),
+                format!("{:?}", address),
+                kind,
+                block,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn meta_key(key: &str, chain_id: u64, token: Address) -> String {
+    format!("{}:{}:{:?}", key, chain_id, token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN_ID: u64 = 1;
+
+    fn token() -> Address {
+        Address::repeat_byte(0x70)
+    }
+
+    fn event(address: Address, change: BlacklistChange, block_number: u64, tx: u8) -> BlacklistEvent {
+        BlacklistEvent {
+            chain_id: CHAIN_ID,
+            token: token(),
+            address,
+            change,
+            block_number,
+            block_hash: None,
+            tx_hash: H256::repeat_byte(tx),
+            log_index: U256::zero(),
+        }
+    }
+
+    #[test]
+    fn status_and_last_block_survive_a_reopen() {
+        let path = std::env::temp_dir().join(format!("blacklist-monitor-store-{}.db", std::process::id()));
+        let (frozen, cleared) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        {
+            let store = Store::open(&path).unwrap();
+            store.save_status(CHAIN_ID, token(), frozen, true, 10).unwrap();
+            store.save_status(CHAIN_ID, token(), cleared, true, 10).unwrap();
+            store.save_status(CHAIN_ID, token(), cleared, false, 12).unwrap();
+            store.set_last_checked_block(CHAIN_ID, token(), 12).unwrap();
+        }
+        let store = Store::open(&path).unwrap();
+        let statuses = store.load_statuses(CHAIN_ID, token()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(statuses, HashMap::from([(frozen, true), (cleared, false)]));
+        assert_eq!(store.last_checked_block(CHAIN_ID, token()).unwrap(), Some(12));
+        // Kept per chain and token
+        assert!(store.load_statuses(10, token()).unwrap().is_empty());
+        assert_eq!(store.last_checked_block(CHAIN_ID, Address::zero()).unwrap(), None);
+    }
+
+    #[test]
+    fn sent_alerts_are_deduplicated() {
+        let store = Store::in_memory();
+        let address = Address::repeat_byte(1);
+        assert!(!store.alert_sent(CHAIN_ID, token(), address, "added", 10).unwrap());
+        store.record_alert(CHAIN_ID, token(), address, "added", 10).unwrap();
+        store.record_alert(CHAIN_ID, token(), address, "added", 10).unwrap();
+        assert!(store.alert_sent(CHAIN_ID, token(), address, "added", 10).unwrap());
+
+        let count: u64 = store.conn().query_row("SELECT COUNT(*) FROM sent_alerts", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+        assert!(!store.alert_sent(CHAIN_ID, token(), address, "removed", 10).unwrap());
+        assert!(!store.alert_sent(CHAIN_ID, token(), address, "added", 11).unwrap());
+    }
+
+    #[test]
+    fn extend_synced_only_moves_over_contiguous_ranges() {
+        let store = Store::in_memory();
+        // Nothing to extend before a backfill
+        store.extend_synced(CHAIN_ID, token(), 1, 100).unwrap();
+        assert_eq!(store.synced_block(CHAIN_ID, token()).unwrap(), None);
+
+        store.set_synced_block(CHAIN_ID, token(), 100).unwrap();
+        store.extend_synced(CHAIN_ID, token(), 101, 200).unwrap();
+        assert_eq!(store.synced_block(CHAIN_ID, token()).unwrap(), Some(200));
+        store.extend_synced(CHAIN_ID, token(), 150, 250).unwrap();
+        assert_eq!(store.synced_block(CHAIN_ID, token()).unwrap(), Some(250));
+        // A gap is left for the next backfill, and older ranges change nothing
+        store.extend_synced(CHAIN_ID, token(), 300, 400).unwrap();
+        store.extend_synced(CHAIN_ID, token(), 10, 50).unwrap();
+        assert_eq!(store.synced_block(CHAIN_ID, token()).unwrap(), Some(250));
+    }
+
+    #[test]
+    fn forget_events_deletes_only_the_reorged_transaction() {
+        let store = Store::in_memory();
+        let address = Address::repeat_byte(1);
+        assert!(store.record_event(&event(address, BlacklistChange::Added, 10, 0xa)).unwrap());
+        assert!(!store.record_event(&event(address, BlacklistChange::Added, 10, 0xa)).unwrap());
+        assert!(store.record_event(&event(address, BlacklistChange::Destroyed(U256::from(5)), 11, 0xb)).unwrap());
+
+        store.forget_events(CHAIN_ID, H256::repeat_byte(0xa)).unwrap();
+        let timeline = store.timeline(address).unwrap();
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(timeline[0].change, BlacklistChange::Destroyed(U256::from(5)));
+        assert_eq!(timeline[0].tx_hash, H256::repeat_byte(0xb));
+        // Other chains keep their events
+        store.forget_events(10, H256::repeat_byte(0xb)).unwrap();
+        assert_eq!(store.timeline(address).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,466 @@
+use ethers::prelude::*;
+use ethers::utils::{format_ether, format_units};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+use crate::client::{ChainClient, SignerClient};
+use crate::error::Error;
+use crate::freeze::FreezeChecker;
+use crate::retry::{Health, RetryPolicy};
+use crate::telegram::TelegramBot;
+use crate::transfer::{self, SweepFees, SweepOutcome, SweepReport, TokenSweepOutcome, TokenSweepReport};
+
+// Settings of the sweeper service
+#[derive(Debug, Clone, Copy)]
+pub struct SweeperOptions {
+    pub recipient: Address,
+    pub min_balance: U256,
+    pub reserve: U256,
+    pub interval: Duration,
+    pub retry: RetryPolicy,
+    // Consecutive failed runs before the "sweeper degraded" alert is sent
+    pub degraded_after: u32,
+    pub dry_run: bool,
+}
+
+/// ERC-20 token swept before the native balance.
+pub struct SweepToken {
+    pub symbol: String,
+    pub address: Address,
+    // Freeze list of the token, when it has one on this chain
+    pub checker: Option<Arc<dyn FreezeChecker>>,
+}
+
+impl SweepToken {
+    /// Resolves token symbols or addresses on the chain of `client`, with their freeze lists.
+    pub fn resolve(client: &ChainClient, checkers: &[Arc<dyn FreezeChecker>], tokens: &[String]) -> crate::error::Result<Vec<Self>> {
+        tokens
+            .iter()
+            .map(|token| {
+                let address = client.token(token)?;
+                let checker = checkers.iter().find(|checker| checker.token() == address).cloned();
+                let symbol = checker.as_ref().map_or_else(|| token.clone(), |checker| checker.name().to_string());
+                Ok(SweepToken { symbol, address, checker })
+            })
+            .collect()
+    }
+
+    fn format(&self, amount: U256) -> String {
+        match &self.checker {
+            Some(checker) => format!("{} {}", format_units(amount, checker.decimals()).unwrap_or_else(|_| amount.to_string()), self.symbol),
+            None => format!("{} {} units", amount, self.symbol),
+        }
+    }
+}
+
+// A sweep that was sent but not confirmed yet
+#[derive(Debug, Clone)]
+struct Unconfirmed {
+    tx_hash: TxHash,
+    // Amount and symbol, e.g. "0.5 ETH"
+    amount: String,
+}
+
+/// Sweeps the token and native balances of one key to the recipient every interval.
+///
+/// A run that sent a transaction waits for its confirmation, and nothing new is sent
+/// until it is confirmed, dropped or reverted. Ctrl-C and SIGTERM stop the service
+/// between runs, after the last sent transaction is resolved; a second one stops it
+/// without waiting any longer.
+pub struct Sweeper<'a> {
+    client: &'a ChainClient,
+    signer: Arc<SignerClient>,
+    options: SweeperOptions,
+    // Swept first, while the native balance still pays for their gas
+    tokens: Vec<SweepToken>,
+    telegram: Option<TelegramBot>,
+    unconfirmed: Option<Unconfirmed>,
+    // Tokens whose freeze list holds the signer or the recipient, alerted once
+    frozen: HashSet<Address>,
+    health: Health,
+}
+
+impl<'a> Sweeper<'a> {
+    pub fn new(
+        client: &'a ChainClient,
+        signer: Arc<SignerClient>,
+        options: SweeperOptions,
+        tokens: Vec<SweepToken>,
+        telegram: Option<TelegramBot>,
+    ) -> Self {
+        Sweeper {
+            client,
+            signer,
+            options,
+            tokens,
+            telegram,
+            unconfirmed: None,
+            frozen: HashSet::new(),
+            health: Health::default(),
+        }
+    }
+
+    pub async fn run(&mut self) -> anyhow::Result<()> {
+        let mut stop = shutdown_requested()?;
+        println_time!(
+            "Sweeping {:?} on {} to {:?} every {:?}{}",
+            self.signer.address(),
+            self.client.chain.name,
+            self.options.recipient,
+            self.options.interval,
+            if self.options.dry_run { " (dry run)" } else { "" }
+        );
+        if self.telegram.is_none() {
+            println_time!("Telegram is not configured, sweeps are only logged");
+        }
+        let symbols: Vec<&str> = self.tokens.iter().map(|token| token.symbol.as_str()).collect();
+        if !symbols.is_empty() {
+            println_time!("Sweeping tokens {} before {}", symbols.join(", "), self.client.chain.native_symbol);
+        }
+        loop {
+            // A run is never interrupted, a shutdown waits for it
+            let result = self.run_once().await;
+            self.settle(result).await;
+            // Marked seen, so the wait below only wakes up for the next request
+            if *stop.borrow_and_update() > 0 {
+                break;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(self.options.interval) => {}
+                _ = stop.changed() => break,
+            }
+        }
+
+        let mut attempt = 0;
+        while let Some(unconfirmed) = self.unconfirmed.clone() {
+            if *stop.borrow() > 1 {
+                println_time!("Stopping without waiting for sweep transaction {:?}", unconfirmed.tx_hash);
+                break;
+            }
+            println_time!("Waiting for sweep transaction {:?} before shutting down, stop again to exit now", unconfirmed.tx_hash);
+            let delay = self.options.retry.delay(attempt);
+            attempt += 1;
+            let wait = async {
+                if let Err(e) = self.confirm().await {
+                    println_time!("Sweep transaction {:?} is not resolved yet: {}", unconfirmed.tx_hash, e);
+                    tokio::time::sleep(delay).await;
+                }
+            };
+            tokio::select! {
+                _ = wait => {}
+                _ = stop.changed() => {}
+            }
+        }
+        println_time!("Sweeper stopped");
+        Ok(())
+    }
+
+    /// Sweeps every token, then the native balance, waiting for each transfer. A token
+    /// that fails does not keep the others or the native balance from being swept.
+    pub async fn run_once(&mut self) -> Result<(), Error> {
+        if self.unconfirmed.is_some() {
+            return self.confirm().await;
+        }
+        let mut first_error = None;
+        for index in 0..self.tokens.len() {
+            if let Err(e) = self.sweep_token(index).await {
+                println_time!("Sweeping {} failed: {}", self.tokens[index].symbol, e);
+                // A transfer that is not resolved yet may still need the native balance
+                if self.unconfirmed.is_some() {
+                    return Err(e);
+                }
+                first_error.get_or_insert(e);
+            }
+        }
+        if let Err(e) = self.sweep_native().await {
+            first_error.get_or_insert(e);
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    async fn sweep_token(&mut self, index: usize) -> Result<(), Error> {
+        let token = &self.tokens[index];
+        let report = transfer::sweep_token(
+            self.client,
+            &self.signer,
+            token.address,
+            token.checker.as_deref(),
+            self.options.recipient,
+            self.options.dry_run,
+        )
+        .await?;
+        println_time!("{} sweep report: {}", token.symbol, self.describe_token(token, &report));
+        match report.outcome {
+            TokenSweepOutcome::Frozen { address } => {
+                if self.frozen.insert(token.address) {
+                    let message = format!(
+                        "🚫 Cannot sweep {} on {}: {:?} is blacklisted",
+                        token.format(report.balance),
+                        self.client.chain.name,
+                        address
+                    );
+                    self.notify(&message).await;
+                }
+            }
+            TokenSweepOutcome::Sent { tx_hash } => {
+                self.frozen.remove(&token.address);
+                self.unconfirmed = Some(Unconfirmed { tx_hash, amount: token.format(report.balance) });
+                self.confirm().await?;
+            }
+            _ => {
+                self.frozen.remove(&token.address);
+            }
+        }
+        Ok(())
+    }
+
+    async fn sweep_native(&mut self) -> Result<(), Error> {
+        let report = transfer::sweep_native(
+            self.client,
+            &self.signer,
+            self.options.recipient,
+            self.options.min_balance,
+            self.options.reserve,
+            self.options.dry_run,
+        )
+        .await?;
+        println_time!("Sweep report: {}", self.describe(&report));
+        if let SweepOutcome::Sent { amount, tx_hash } = report.outcome {
+            let amount = format!("{} {}", format_ether(amount), self.client.chain.native_symbol);
+            self.unconfirmed = Some(Unconfirmed { tx_hash, amount });
+            self.confirm().await?;
+        }
+        Ok(())
+    }
+
+    // Wait for the unconfirmed sweep. It is kept while the node cannot tell what
+    // happened to it, and forgotten once it is confirmed, dropped or reverted.
+    async fn confirm(&mut self) -> Result<(), Error> {
+        let Some(unconfirmed) = self.unconfirmed.clone() else { return Ok(()) };
+        let chain = &self.client.chain;
+        match transfer::wait_for(self.client, unconfirmed.tx_hash, "Sweep").await {
+            Ok(receipt) => {
+                self.unconfirmed = None;
+                self.notify(&format!(
+                    "💸 Swept {} from {:?} to {:?} on {} in block {} (tx {:?})",
+                    unconfirmed.amount,
+                    self.signer.address(),
+                    self.options.recipient,
+                    chain.name,
+                    receipt.block_number.unwrap_or_default(),
+                    unconfirmed.tx_hash
+                ))
+                .await;
+                Ok(())
+            }
+            Err(e @ (Error::Rpc(_) | Error::InvalidResponse(_))) => Err(e),
+            Err(e) => {
+                self.unconfirmed = None;
+                self.notify(&format!(
+                    "⚠️ Sweep of {} on {} failed (tx {:?}): {}",
+                    unconfirmed.amount,
+                    chain.name,
+                    unconfirmed.tx_hash,
+                    e
+                ))
+                .await;
+                Err(e)
+            }
+        }
+    }
+
+    fn describe(&self, report: &SweepReport) -> String {
+        let symbol = &self.client.chain.native_symbol;
+        let outcome = match report.outcome {
+            SweepOutcome::BelowMinimum => format!("below the minimum of {} {}", format_ether(self.options.min_balance), symbol),
+            SweepOutcome::GasNotCovered => format!("does not cover the gas and the reserve of {} {}", format_ether(self.options.reserve), symbol),
+            SweepOutcome::DryRun { amount } => format!("would send {} {}", format_ether(amount), symbol),
+            SweepOutcome::Sent { amount, tx_hash } => format!("sent {} {} in {:?}", format_ether(amount), symbol, tx_hash),
+        };
+        let gwei = |value: U256| format_units(value, "gwei").unwrap_or_else(|_| value.to_string());
+        let fees = match report.fees {
+            SweepFees::Legacy { gas_price } => format!("gas price {} gwei", gwei(gas_price)),
+            SweepFees::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => {
+                format!("max fee {} gwei, priority fee {} gwei", gwei(max_fee_per_gas), gwei(max_priority_fee_per_gas))
+            }
+        };
+        format!(
+            "balance {} {}, gas cost {} {} ({}), {}",
+            format_ether(report.balance),
+            symbol,
+            format_ether(report.gas_cost),
+            symbol,
+            fees,
+            outcome
+        )
+    }
+
+    fn describe_token(&self, token: &SweepToken, report: &TokenSweepReport) -> String {
+        let symbol = &self.client.chain.native_symbol;
+        let outcome = match report.outcome {
+            TokenSweepOutcome::Empty => "nothing to send".to_string(),
+            TokenSweepOutcome::Frozen { address } => format!("{:?} is blacklisted", address),
+            TokenSweepOutcome::GasNotCovered { gas_cost } => {
+                format!("the {} balance does not cover the gas cost of {} {}", symbol, format_ether(gas_cost), symbol)
+            }
+            TokenSweepOutcome::DryRun { gas_cost } => format!("would send it for at most {} {}", format_ether(gas_cost), symbol),
+            TokenSweepOutcome::Sent { tx_hash } => format!("sent in {:?}", tx_hash),
+        };
+        format!("balance {}, {}", token.format(report.balance), outcome)
+    }
+
+    // Alerts once when the sweeper degrades and once when it recovers, like the monitor
+    async fn settle(&mut self, result: Result<(), Error>) {
+        match result {
+            Ok(()) => {
+                if self.health.succeeded().is_some() {
+                    self.notify(&format!("✅ Sweeper on {} recovered", self.client.chain.name)).await;
+                }
+            }
+            Err(e) => {
+                let class = e.class();
+                let degraded = self.health.failed(class, self.options.degraded_after);
+                let failures = self.health.consecutive_failures();
+                println_time!("Sweep on {} failed ({:?}, {} in a row): {}", self.client.chain.name, class, failures, e);
+                if degraded {
+                    self.notify(&format!("🚨 Sweeper on {} is failing ({} run(s) in a row): {}", self.client.chain.name, failures, e))
+                        .await;
+                }
+            }
+        }
+    }
+
+    // Best effort; a lost notification never stops the sweeper
+    async fn notify(&self, message: &str) {
+        println_time!("{}", message);
+        let Some(telegram) = &self.telegram else { return };
+        let result = self
+            .options
+            .retry
+            .run("Sending a Telegram message", move || async move { Ok(telegram.send_message(message).await?) })
+            .await;
+        if let Err(e) = result {
+            println_time!("Failed to send the sweep notification: {:#}", e);
+        }
+    }
+}
+
+// Counts Ctrl-C and SIGTERM up to the second one, which stops waiting for a sent sweep
+fn shutdown_requested() -> anyhow::Result<watch::Receiver<u32>> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let (sender, receiver) = watch::channel(0);
+    tokio::spawn(async move {
+        for requests in 1..=2 {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+            if requests == 1 {
+                println_time!("Shutdown requested, finishing the current sweep");
+            }
+            sender.send_replace(requests);
+        }
+    });
+    Ok(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::freeze::{CheckerSpec, FreezeList, SignatureChecker};
+    use crate::testing::{self, calls, rpc_server, Requests, RpcHandler};
+
+    const TOKEN: Address = Address::repeat_byte(0x70);
+    const RECIPIENT: Address = Address::repeat_byte(0xbb);
+    // balanceOf(address)
+    const BALANCE_OF: &str = "0x70a08231";
+
+    fn word(value: U256) -> serde_json::Value {
+        json!(format!("0x{:064x}", value))
+    }
+
+    // Node of a chain without a base fee at 1 gwei, where the signer holds `native` wei and
+    // 100 token units, and is frozen when `frozen` is set
+    async fn node(native: u64, frozen: bool) -> (ChainClient, Arc<SignerClient>, Requests) {
+        let handler: Arc<RpcHandler> = Arc::new(move |method, params| match method {
+            "eth_getBlockByNumber" => Ok(json!({ "number": "0x10", "hash": H256::repeat_byte(1) })),
+            "eth_gasPrice" => Ok(word(U256::exp10(9))),
+            "eth_estimateGas" => Ok(word(U256::from(60_000))),
+            "eth_getBalance" => Ok(word(U256::from(native))),
+            "eth_call" => {
+                let data = params[0]["data"].as_str().or(params[0]["input"].as_str()).unwrap_or_default();
+                match data.starts_with(BALANCE_OF) {
+                    true => Ok(word(U256::from(100))),
+                    // Only the signer is ever frozen, the recipient never
+                    false => Ok(word(U256::from((frozen && !data.contains(&hex::encode(RECIPIENT))) as u8))),
+                }
+            }
+            _ => Err(json!({ "code": -32601, "message": "method not found" })),
+        });
+        let (url, received) = rpc_server(handler).await;
+        let client = testing::client(&url, 42161);
+        let signer = client.signer(&format!("{:064x}", 1)).unwrap();
+        (client, signer, received)
+    }
+
+    fn options() -> SweeperOptions {
+        SweeperOptions {
+            recipient: RECIPIENT,
+            min_balance: U256::exp10(18),
+            reserve: U256::zero(),
+            interval: Duration::from_secs(60),
+            retry: RetryPolicy { attempts: 1, base_delay: Duration::ZERO, max_delay: Duration::ZERO },
+            degraded_after: 3,
+            dry_run: false,
+        }
+    }
+
+    fn usdt(client: &ChainClient) -> SweepToken {
+        let spec = CheckerSpec::builtin("USDT", FreezeList::TetherV2, TOKEN);
+        let checker: Arc<dyn FreezeChecker> = Arc::new(SignatureChecker::new(spec, client.chain.clone()).unwrap());
+        SweepToken { symbol: "USDT".to_string(), address: TOKEN, checker: Some(checker) }
+    }
+
+    fn sent(received: &Requests) -> usize {
+        calls(received, "eth_sendRawTransaction").len() + calls(received, "eth_sendTransaction").len()
+    }
+
+    #[tokio::test]
+    async fn skips_a_frozen_token_and_alerts_once() {
+        let (client, signer, received) = node(0, true).await;
+        let checker = usdt(&client).checker.unwrap();
+        let report = transfer::sweep_token(&client, &signer, TOKEN, Some(checker.as_ref()), RECIPIENT, false).await.unwrap();
+        assert_eq!(report.outcome, TokenSweepOutcome::Frozen { address: signer.address() });
+        // Nothing is estimated for a transfer that would revert
+        assert!(calls(&received, "eth_estimateGas").is_empty());
+
+        let (telegram, messages) = testing::telegram(200).await;
+        let mut sweeper = Sweeper::new(&client, signer.clone(), options(), vec![usdt(&client)], Some(telegram));
+        sweeper.run_once().await.unwrap();
+        sweeper.run_once().await.unwrap();
+        let messages = testing::messages(&messages);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].starts_with("🚫 Cannot sweep 0.000100 USDT on Arbitrum One"), "{}", messages[0]);
+        assert_eq!(sent(&received), 0);
+    }
+
+    #[tokio::test]
+    async fn skips_tokens_while_the_native_balance_does_not_cover_the_gas() {
+        // 60k gas at 1 gwei costs 60_000 gwei
+        let (client, signer, received) = node(59_999_000_000_000, false).await;
+        let report = transfer::sweep_token(&client, &signer, TOKEN, None, RECIPIENT, false).await.unwrap();
+        assert_eq!(report.outcome, TokenSweepOutcome::GasNotCovered { gas_cost: U256::from(60_000_000_000_000u64) });
+
+        let (telegram, messages) = testing::telegram(200).await;
+        let mut sweeper = Sweeper::new(&client, signer, options(), vec![usdt(&client)], Some(telegram));
+        sweeper.run_once().await.unwrap();
+        assert!(sweeper.unconfirmed.is_none());
+        assert!(testing::messages(&messages).is_empty());
+        assert_eq!(sent(&received), 0);
+    }
+}
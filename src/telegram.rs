@@ -1,30 +1,83 @@
-use reqwest;
-use serde_json::json;
-use std::error::Error;
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+
+const API_URL: &str = "https://api.telegram.org";
+
+/// Sends messages to one Telegram chat through the Bot API, over one HTTP client.
+#[derive(Clone)]
 pub struct TelegramBot {
+    http: reqwest::Client,
+    api_url: String,
     bot_token: String,
     chat_id: String,
 }
+
 impl TelegramBot {
     pub fn new(bot_token: String, chat_id: String) -> Self {
-        TelegramBot { bot_token, chat_id }
+        Self::with_api_url(API_URL.to_string(), bot_token, chat_id)
     }
-    pub async fn send_message(&self, text: &str) -> Result<(), Box<dyn Error>> {
-        let client = reqwest::Client::new();
-        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
 
-        let response = client.post(&url)
+    /// Bot served by another Bot API server than api.telegram.org.
+    pub fn with_api_url(api_url: String, bot_token: String, chat_id: String) -> Self {
+        TelegramBot { http: reqwest::Client::new(), api_url, bot_token, chat_id }
+    }
+
+    pub async fn send_message(&self, text: &str) -> Result<()> {
+        let url = format!("{}/bot{}/sendMessage", self.api_url, self.bot_token);
+
+        // Errors carry the request URL, which contains the bot token
+        let response = self
+            .http
+            .post(&url)
             .json(&json!({
                 "chat_id": self.chat_id,
                 "text": text
             }))
             .send()
-            .await?;
-        if response.status().is_success() {
-            println!("Message sent successfully!");
-        } else {
-            println!("Failed to send message: {:?}", response.text().await?);
+            .await
+            .map_err(|e| Error::Notification(e.without_url()))?;
+        let status = response.status();
+        if !status.is_success() {
+            // The Bot API explains the rejection in `description`
+            let body = response.text().await.unwrap_or_default();
+            let description = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|reply| reply["description"].as_str().map(str::to_string))
+                .unwrap_or(body);
+            return Err(Error::NotificationRejected { status: status.as_u16(), description });
         }
+        println_time!("✅ Telegram message sent.");
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::ErrorClass;
+    use crate::testing::{self, messages};
+
+    #[tokio::test]
+    async fn posts_the_message_to_the_chat() {
+        let (telegram, received) = testing::telegram(200).await;
+        telegram.send_message("hello").await.unwrap();
+
+        assert_eq!(messages(&received), ["hello"]);
+        let request = &received.lock().unwrap()[0];
+        assert_eq!(request.path, "/bottoken/sendMessage");
+        assert_eq!(request.json()["chat_id"], "chat");
+    }
+
+    #[tokio::test]
+    async fn returns_the_description_of_a_rejection() {
+        let (telegram, _) = testing::telegram(400).await;
+        let error = telegram.send_message("hello").await.unwrap_err();
+
+        assert!(matches!(&error, Error::NotificationRejected { status: 400, description } if description == "Bad Request: chat not found"));
+        assert_eq!(error.class(), ErrorClass::Fatal);
+
+        let (telegram, _) = testing::telegram(429).await;
+        assert_eq!(telegram.send_message("hello").await.unwrap_err().class(), ErrorClass::Transient);
+    }
+}
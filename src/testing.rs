@@ -0,0 +1,164 @@
+//! Local HTTP servers standing in for chain nodes, relays and Telegram in tests.
+
+use ethers::abi::{self, Token};
+use ethers::prelude::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::chains;
+use crate::client::{ChainClient, ChainProvider, RpcSettings};
+use crate::relay::{RelayMethod, RelaySettings};
+use crate::rpc::ProviderPool;
+use crate::telegram::TelegramBot;
+
+/// A request received by a mock server, with lowercase header names.
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Received {
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).expect("JSON body")
+    }
+
+    pub fn method(&self) -> String {
+        self.json()["method"].as_str().unwrap_or_default().to_string()
+    }
+}
+
+pub type Requests = Arc<Mutex<Vec<Received>>>;
+
+/// Answers a request with an HTTP status and a JSON body.
+pub type HttpHandler = dyn Fn(&Received) -> (u16, Value) + Send + Sync;
+
+/// Returns the result or the error of a JSON-RPC method called with its params.
+pub type RpcHandler = dyn Fn(&str, &Value) -> Result<Value, Value> + Send + Sync;
+
+/// HTTP server on a local port answering every request with `handler`.
+pub async fn http_server(handler: Arc<HttpHandler>) -> (String, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let received: Requests = Arc::default();
+    let log = received.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let (handler, log) = (handler.clone(), log.clone());
+            tokio::spawn(async move {
+                let mut data = Vec::new();
+                let mut buf = [0u8; 4096];
+                let (head, body) = loop {
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    data.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&data).to_string();
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else { continue };
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break (head.to_string(), body.to_string());
+                    }
+                };
+                let path = head.split_whitespace().nth(1).unwrap_or_default().to_string();
+                let headers = head
+                    .lines()
+                    .skip(1)
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+                    .collect();
+                let received = Received { path, headers, body };
+                let (status, reply) = handler(&received);
+                log.lock().unwrap().push(received);
+                let reply = reply.to_string();
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    reply.len(),
+                    reply
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    (url, received)
+}
+
+/// JSON-RPC server on a local port answering every call with `handler`.
+pub async fn rpc_server(handler: Arc<RpcHandler>) -> (String, Requests) {
+    http_server(Arc::new(move |received: &Received| {
+        let request = received.json();
+        let reply = match handler(request["method"].as_str().unwrap_or_default(), &request["params"]) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": request["id"], "error": error }),
+        };
+        (200, reply)
+    }))
+    .await
+}
+
+/// The JSON-RPC calls of `method` received so far.
+pub fn calls(received: &Requests, method: &str) -> Vec<Received> {
+    received.lock().unwrap().iter().filter(|request| request.method() == method).cloned().collect()
+}
+
+/// Return data of a Multicall3 `aggregate3` call with the given (success, returnData) results.
+pub fn aggregate3(results: Vec<(bool, Vec<u8>)>) -> Value {
+    let results = results
+        .into_iter()
+        .map(|(success, data)| Token::Tuple(vec![Token::Bool(success), Token::Bytes(data)]))
+        .collect();
+    json!(Bytes::from(abi::encode(&[Token::Array(results)])))
+}
+
+/// Return data of a freeze status check.
+pub fn frozen(frozen: bool) -> Vec<u8> {
+    abi::encode(&[Token::Bool(frozen)])
+}
+
+/// Provider of built-in chain `chain_id` whose only RPC endpoint is `url`.
+pub fn provider(url: &str, chain_id: u64) -> ChainProvider {
+    let mut chain = chains::builtin(chain_id).unwrap();
+    chain.rpc_urls = vec![url.to_string()];
+    Provider::new(ProviderPool::new(&chain, Duration::from_secs(5)).unwrap())
+}
+
+/// Client of built-in chain `chain_id` whose only RPC endpoint is `url`, without relays.
+pub fn client(url: &str, chain_id: u64) -> ChainClient {
+    let mut chain = chains::builtin(chain_id).unwrap();
+    chain.rpc_urls = vec![url.to_string()];
+    chain.relay_urls.clear();
+    let rpc = RpcSettings { timeout: Duration::from_secs(5), health_check_interval: Duration::from_secs(3600) };
+    let relay = RelaySettings { method: RelayMethod::Bundle, target_blocks: 1, fallback_public: true };
+    ChainClient::connect(Arc::new(chain), rpc, relay).unwrap()
+}
+
+/// Bot API server taking every message, or rejecting them with HTTP `status`.
+pub async fn telegram(status: u16) -> (TelegramBot, Requests) {
+    let (url, received) = http_server(Arc::new(move |_: &Received| match status {
+        200 => (200, json!({ "ok": true, "result": {} })),
+        _ => (status, json!({ "ok": false, "error_code": status, "description": "Bad Request: chat not found" })),
+    }))
+    .await;
+    (TelegramBot::with_api_url(url, "token".to_string(), "chat".to_string()), received)
+}
+
+/// Texts of the messages a Bot API server received.
+pub fn messages(received: &Requests) -> Vec<String> {
+    received
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| request.json()["text"].as_str().unwrap_or_default().to_string())
+        .collect()
+}
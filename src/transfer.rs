@@ -0,0 +1,556 @@
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::format_ether;
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::client::{ChainClient, ChainProvider, SignerClient};
+use crate::contracts::{Erc20, GasPriceOracle, GAS_PRICE_ORACLE_ADDRESS};
+use crate::error::{Error, Result};
+use crate::freeze::FreezeChecker;
+
+// Recent blocks and reward percentile sampled with eth_feeHistory for the priority fee
+const FEE_HISTORY_BLOCKS: u64 = 10;
+const PRIORITY_FEE_PERCENTILE: f64 = 50.0;
+// Priority fee when every sampled block was empty: 0.1 gwei
+const FALLBACK_PRIORITY_FEE: u64 = 100_000_000;
+
+/// Result of one look at the signer's native balance.
+#[derive(Debug, Clone, Copy)]
+pub struct SweepReport {
+    pub balance: U256,
+    // Most the transfer can cost, which is what it costs when it lands in time, including
+    // the L1 data fee on OP Stack chains
+    pub gas_cost: U256,
+    pub fees: SweepFees,
+    pub outcome: SweepOutcome,
+}
+
+/// Gas price of a sweep, per unit of gas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SweepFees {
+    // Chains without a base fee
+    Legacy { gas_price: U256 },
+    Eip1559 { max_fee_per_gas: U256, max_priority_fee_per_gas: U256 },
+}
+
+impl SweepFees {
+    /// Highest price per gas the transaction may be charged.
+    pub fn max_gas_price(&self) -> U256 {
+        match *self {
+            SweepFees::Legacy { gas_price } => gas_price,
+            SweepFees::Eip1559 { max_fee_per_gas, .. } => max_fee_per_gas,
+        }
+    }
+
+    // Transaction of the type these fees belong to, without gas limit
+    fn request(&self, from: Address, to: Address, value: U256, data: Bytes) -> TypedTransaction {
+        match *self {
+            SweepFees::Legacy { gas_price } => TransactionRequest::new()
+                .from(from)
+                .to(to)
+                .value(value)
+                .data(data)
+                .gas_price(gas_price)
+                .into(),
+            SweepFees::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => Eip1559TransactionRequest::new()
+                .from(from)
+                .to(to)
+                .value(value)
+                .data(data)
+                .max_fee_per_gas(max_fee_per_gas)
+                .max_priority_fee_per_gas(max_priority_fee_per_gas)
+                .into(),
+        }
+    }
+}
+
+/// Estimates sweep fees from the base fee of the next block and the median priority fee
+/// paid in recent blocks, falling back to `eth_gasPrice` on chains without a base fee.
+///
+/// A sweep must be charged exactly its fee cap to leave nothing behind, so the priority
+/// fee takes whatever the base fee leaves of the cap. The cap covers the base fee rising
+/// by the protocol maximum of 1/8 once, so the transaction is charged the full cap in
+/// either of the next two blocks; if it lands later at a lower base fee, the difference
+/// stays in the account.
+pub async fn estimate_sweep_fees(client: &ChainClient) -> Result<SweepFees> {
+    let provider = client.provider();
+    let latest = provider
+        .get_block(BlockNumber::Latest)
+        .await?
+        .ok_or_else(|| Error::InvalidResponse("no latest block".to_string()))?;
+    if latest.base_fee_per_gas.is_none() {
+        return Ok(SweepFees::Legacy { gas_price: provider.get_gas_price().await? });
+    }
+    let history = provider
+        .fee_history(FEE_HISTORY_BLOCKS, BlockNumber::Latest, &[PRIORITY_FEE_PERCENTILE])
+        .await?;
+    // The last base fee is the one of the next block
+    let next_base_fee = *history
+        .base_fee_per_gas
+        .last()
+        .ok_or_else(|| Error::InvalidResponse("eth_feeHistory returned no base fee".to_string()))?;
+    let rewards = history.reward.iter().filter_map(|rewards| rewards.first().copied()).collect();
+    Ok(sweep_fees(next_base_fee, rewards))
+}
+
+// Fee cap of `estimate_sweep_fees`: the next base fee raised by 1/8, plus the median of the
+// non-zero `rewards`, all of which above the base fee goes to the priority fee
+fn sweep_fees(next_base_fee: U256, mut rewards: Vec<U256>) -> SweepFees {
+    rewards.retain(|reward| !reward.is_zero());
+    rewards.sort();
+    let priority_fee = rewards.get(rewards.len() / 2).copied().unwrap_or(U256::from(FALLBACK_PRIORITY_FEE));
+    let max_fee_per_gas = next_base_fee + next_base_fee / 8 + priority_fee;
+    SweepFees::Eip1559 {
+        max_fee_per_gas,
+        max_priority_fee_per_gas: max_fee_per_gas - next_base_fee,
+    }
+}
+
+// Most a transaction of `gas` can cost at `fees`. The L1 data fee quoted by the oracle of
+// an OP Stack chain gets a quarter on top in case it rises before the transaction lands;
+// what is not charged stays in the account.
+fn worst_case_cost(fees: SweepFees, gas: U256, l1_data_fee: U256) -> Result<U256> {
+    let gas_cost = gas
+        .checked_mul(fees.max_gas_price())
+        .ok_or_else(|| Error::InvalidResponse(format!("gas {} at {} per gas overflows", gas, fees.max_gas_price())))?;
+    Ok(gas_cost + l1_data_fee + l1_data_fee / 4)
+}
+
+// What a sweep of `balance` sends once `gas_cost` and `reserve` are left, none when they
+// take it all
+fn sweep_amount(balance: U256, gas_cost: U256, reserve: U256) -> Option<U256> {
+    balance.checked_sub(gas_cost + reserve).filter(|amount| !amount.is_zero())
+}
+
+/// Fees for a transaction that must land in the next block whatever the competition:
+/// `priority_fee` on top of twice the latest base fee, which stays above the base fee
+/// after five full blocks in a row, or on top of `eth_gasPrice` on chains without one.
+pub async fn estimate_rescue_fees(client: &ChainClient, priority_fee: U256) -> Result<SweepFees> {
+    let provider = client.provider();
+    let latest = provider
+        .get_block(BlockNumber::Latest)
+        .await?
+        .ok_or_else(|| Error::InvalidResponse("no latest block".to_string()))?;
+    Ok(match latest.base_fee_per_gas {
+        Some(base_fee) => SweepFees::Eip1559 {
+            max_fee_per_gas: base_fee * 2 + priority_fee,
+            max_priority_fee_per_gas: priority_fee,
+        },
+        None => SweepFees::Legacy { gas_price: provider.get_gas_price().await? + priority_fee },
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SweepOutcome {
+    BelowMinimum,
+    GasNotCovered,
+    // Nothing was signed
+    DryRun { amount: U256 },
+    // Sent, not confirmed yet
+    Sent { amount: U256, tx_hash: TxHash },
+}
+
+/// Sends the whole native balance of the signer to `recipient`, minus the gas cost and
+/// `reserve`, without waiting for the confirmation.
+///
+/// Nothing is sent while the balance is below `min_balance` or does not cover the gas
+/// and the reserve, or when `dry_run` is set.
+pub async fn sweep_native(
+    client: &ChainClient,
+    signer: &Arc<SignerClient>,
+    recipient: Address,
+    min_balance: U256,
+    reserve: U256,
+    dry_run: bool,
+) -> Result<SweepReport> {
+    let symbol = &client.chain.native_symbol;
+    println_time!("Checking {} balance on {}...", symbol, client.chain.name);
+    let fees = estimate_sweep_fees(client).await?;
+    let balance = signer.get_balance(signer.address(), None).await?;
+    println_time!("Current balance: {} {}", format_ether(balance), symbol);
+    // The node's estimate covers recipients that are contracts, and on Arbitrum the L1 cost.
+    // It is made without fees, which the whole balance as value leaves nothing for.
+    let estimate: TypedTransaction = TransactionRequest::new().from(signer.address()).to(recipient).value(balance).into();
+    let gas_limit = client.provider().estimate_gas(&estimate, None).await?;
+    let mut tx = fees.request(signer.address(), recipient, balance, Bytes::new());
+    tx.set_gas(gas_limit);
+    let gas_cost = worst_case_cost(fees, gas_limit, l1_data_fee(client, &tx).await?)?;
+    println_time!("Estimated gas cost: {} {}", format_ether(gas_cost), symbol);
+    let report = |outcome| SweepReport { balance, gas_cost, fees, outcome };
+    if balance < min_balance {
+        println_time!("Balance below minimum threshold ({} {})", format_ether(min_balance), symbol);
+        return Ok(report(SweepOutcome::BelowMinimum));
+    }
+    let Some(amount) = sweep_amount(balance, gas_cost, reserve) else {
+        println_time!("Insufficient balance to cover gas costs and the reserve of {} {}", format_ether(reserve), symbol);
+        return Ok(report(SweepOutcome::GasNotCovered));
+    };
+    if dry_run {
+        println_time!("Dry run: would transfer {} {} to {:?}", format_ether(amount), symbol, recipient);
+        return Ok(report(SweepOutcome::DryRun { amount }));
+    }
+    println_time!("Preparing to transfer {} {}", format_ether(amount), symbol);
+
+    tx.set_value(amount);
+    let tx_hash = send(client, signer, tx).await?;
+    println_time!("Transfer transaction sent: {:?}", tx_hash);
+    Ok(report(SweepOutcome::Sent { amount, tx_hash }))
+}
+
+/// Result of one look at the signer's balance of a token.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenSweepReport {
+    // In the token's smallest unit
+    pub balance: U256,
+    pub outcome: TokenSweepOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenSweepOutcome {
+    Empty,
+    // The token's freeze list holds `address`, the signer or the recipient
+    Frozen { address: Address },
+    // The native balance is below the most the transfer can cost
+    GasNotCovered { gas_cost: U256 },
+    DryRun { gas_cost: U256 },
+    Sent { tx_hash: TxHash },
+}
+
+/// Sends the whole `token` balance of the signer to `recipient`, without waiting for the
+/// confirmation.
+///
+/// Nothing is sent when the balance is zero, when `checker` reports the signer or the
+/// recipient as frozen, when the native balance does not cover the gas, or when `dry_run`
+/// is set. The gas estimate executes the transfer, so a revert fails before sending.
+pub async fn sweep_token(
+    client: &ChainClient,
+    signer: &Arc<SignerClient>,
+    token: Address,
+    checker: Option<&dyn FreezeChecker>,
+    recipient: Address,
+    dry_run: bool,
+) -> Result<TokenSweepReport> {
+    transfer_balance(client, signer, token, checker, recipient, estimate_sweep_fees(client), dry_run).await
+}
+
+/// Sends the whole `token` balance of the signer to `recipient` with `priority_fee` per gas
+/// on top of the base fee, without waiting for the confirmation.
+///
+/// Made for a balance that was just unfrozen, so no freeze list is read: the gas estimate
+/// executes the transfer and fails if it would revert. Nothing is sent when `dry_run` is set.
+pub async fn rescue_token(
+    client: &ChainClient,
+    signer: &Arc<SignerClient>,
+    token: Address,
+    recipient: Address,
+    priority_fee: U256,
+    dry_run: bool,
+) -> Result<TokenSweepReport> {
+    transfer_balance(client, signer, token, None, recipient, estimate_rescue_fees(client, priority_fee), dry_run).await
+}
+
+// Transfers the whole token balance with `fees`, only estimated once there is something to send
+async fn transfer_balance(
+    client: &ChainClient,
+    signer: &Arc<SignerClient>,
+    token: Address,
+    checker: Option<&dyn FreezeChecker>,
+    recipient: Address,
+    fees: impl Future<Output = Result<SweepFees>>,
+    dry_run: bool,
+) -> Result<TokenSweepReport> {
+    let from = signer.address();
+    let balance = client.erc20(token).balance_of(from).call().await?;
+    let report = |outcome| TokenSweepReport { balance, outcome };
+    if balance.is_zero() {
+        return Ok(report(TokenSweepOutcome::Empty));
+    }
+    if let Some(checker) = checker {
+        for address in [from, recipient] {
+            if is_frozen(client, checker, address).await? {
+                return Ok(report(TokenSweepOutcome::Frozen { address }));
+            }
+        }
+    }
+
+    let data = Erc20::new(token, signer.clone())
+        .transfer(recipient, balance)
+        .calldata()
+        .expect("transfer is a function call");
+    let fees = fees.await?;
+    let mut tx = fees.request(from, token, U256::zero(), data);
+    let gas = client.provider().estimate_gas(&tx, None).await?;
+    tx.set_gas(gas);
+    let gas_cost = worst_case_cost(fees, gas, l1_data_fee(client, &tx).await?)?;
+    if signer.get_balance(from, None).await? < gas_cost {
+        return Ok(report(TokenSweepOutcome::GasNotCovered { gas_cost }));
+    }
+    if dry_run {
+        return Ok(report(TokenSweepOutcome::DryRun { gas_cost }));
+    }
+    let tx_hash = send(client, signer, tx).await?;
+    println_time!("Token transfer transaction sent: {:?}", tx_hash);
+    Ok(report(TokenSweepOutcome::Sent { tx_hash }))
+}
+
+/// Transfer of `amount` of `token` to `recipient`, signed with `nonce` and ready for
+/// `eth_sendRawTransaction`, along with its hash.
+pub async fn sign_token_transfer(
+    signer: &Arc<SignerClient>,
+    token: Address,
+    recipient: Address,
+    amount: U256,
+    nonce: U256,
+    gas: U256,
+    fees: SweepFees,
+) -> Result<(TxHash, Bytes)> {
+    let data = Erc20::new(token, signer.clone())
+        .transfer(recipient, amount)
+        .calldata()
+        .expect("transfer is a function call");
+    let mut tx = fees.request(signer.address(), token, U256::zero(), data);
+    tx.set_nonce(nonce);
+    tx.set_gas(gas);
+    tx.set_chain_id(signer.signer().chain_id());
+    sign(signer, &tx).await
+}
+
+async fn sign(signer: &SignerClient, tx: &TypedTransaction) -> Result<(TxHash, Bytes)> {
+    let signature = signer
+        .signer()
+        .sign_transaction(tx)
+        .await
+        .map_err(|e| Error::Signing(e.to_string()))?;
+    Ok((tx.hash(&signature), tx.rlp_signed(&signature)))
+}
+
+// Sends a sweep or rescue through the chain's private relays when it has any, which
+// return once it is mined or broadcast publicly, or through the public mempool otherwise
+async fn send(client: &ChainClient, signer: &SignerClient, mut tx: TypedTransaction) -> Result<TxHash> {
+    let Some(relay) = client.relay() else {
+        return Ok(signer.send_transaction(tx, None).await?.tx_hash());
+    };
+    signer.fill_transaction(&mut tx, None).await?;
+    let (tx_hash, raw) = sign(signer, &tx).await?;
+    relay.submit(&client.provider(), &raw, tx_hash).await?;
+    Ok(tx_hash)
+}
+
+// L1 data fee an OP Stack chain charges on top of the gas, zero elsewhere, as the oracle
+// quotes it for the unsigned transaction
+async fn l1_data_fee(client: &ChainClient, tx: &TypedTransaction) -> Result<U256> {
+    if !client.chain.op_stack {
+        return Ok(U256::zero());
+    }
+    let provider = client.provider();
+    let mut tx = tx.clone();
+    if let Some(from) = tx.from().copied() {
+        tx.set_nonce(provider.get_transaction_count(from, None).await?);
+    }
+    tx.set_chain_id(client.chain.chain_id);
+    let oracle = GasPriceOracle::new(GAS_PRICE_ORACLE_ADDRESS.parse::<Address>().expect("valid address"), provider);
+    Ok(oracle.get_l1_fee(tx.rlp()).call().await?)
+}
+
+/// Reads the freeze status of `address` directly from the token, at the latest block.
+pub async fn is_frozen(client: &ChainClient, checker: &dyn FreezeChecker, address: Address) -> Result<bool> {
+    let call: TypedTransaction = TransactionRequest::new()
+        .to(checker.token())
+        .data(checker.encode_check(address))
+        .into();
+    let data = client.provider().call(&call, None).await?;
+    checker
+        .decode_check(&data)
+        .ok_or_else(|| Error::InvalidResponse(format!("{} freeze status of {:?}: {}", checker.name(), address, data)))
+}
+
+/// Waits for the confirmation of a transaction sent earlier by this process, such as a
+/// sweep of an earlier iteration or a broadcast pre-signed rescue.
+pub async fn wait_for(client: &ChainClient, tx_hash: TxHash, what: &str) -> Result<TransactionReceipt> {
+    let provider = client.provider();
+    confirm(client, PendingTransaction::new(tx_hash, &provider), what).await
+}
+
+/// Transfers `amount` (in the token's smallest unit) of `token` from the signer to `to`.
+pub async fn transfer_token(
+    client: &ChainClient,
+    signer: &Arc<SignerClient>,
+    token: Address,
+    to: Address,
+    amount: U256,
+    dry_run: bool,
+) -> Result<Option<TxHash>> {
+    println_time!("Transferring {} of token {:?} from {:?} to {:?}", amount, token, signer.address(), to);
+    let contract = Erc20::new(token, signer.clone());
+    submit(client, contract.transfer(to, amount), "Transfer", dry_run).await
+}
+
+/// Allows `spender` to move up to `amount` of the signer's `token` with `transferFrom`.
+pub async fn approve_spender(
+    client: &ChainClient,
+    signer: &Arc<SignerClient>,
+    token: Address,
+    spender: Address,
+    amount: U256,
+    dry_run: bool,
+) -> Result<Option<TxHash>> {
+    println_time!("Approving {:?} to spend {} of token {:?} owned by {:?}", spender, amount, token, signer.address());
+    let contract = Erc20::new(token, signer.clone());
+    submit(client, contract.approve(spender, amount), "Approve", dry_run).await
+}
+
+/// Moves `amount` of `token` from `from` to `to` with the signer's allowance.
+///
+/// `from` must have approved the signer for at least `amount` beforehand.
+pub async fn execute_transfer_from(
+    client: &ChainClient,
+    signer: &Arc<SignerClient>,
+    token: Address,
+    from: Address,
+    to: Address,
+    amount: U256,
+    dry_run: bool,
+) -> Result<Option<TxHash>> {
+    let allowance = client.erc20(token).allowance(from, signer.address()).call().await?;
+    if allowance < amount {
+        return Err(Error::InsufficientAllowance { owner: from, spender: signer.address(), allowance, amount });
+    }
+    println_time!("Transferring {} of token {:?} from {:?} to {:?} as {:?}", amount, token, from, to, signer.address());
+    let contract = Erc20::new(token, signer.clone());
+    submit(client, contract.transfer_from(from, to, amount), "TransferFrom", dry_run).await
+}
+
+// Sends a token call and waits for its confirmation. A dry run only estimates its gas,
+// which executes the call on the latest state and fails the same way sending would.
+async fn submit(
+    client: &ChainClient,
+    call: ContractCall<SignerClient, bool>,
+    what: &str,
+    dry_run: bool,
+) -> Result<Option<TxHash>> {
+    if dry_run {
+        let gas = call.estimate_gas().await?;
+        println_time!("Dry run: {} transaction would succeed using about {} gas", what, gas);
+        return Ok(None);
+    }
+    let pending_tx = call.send().await?;
+    println_time!("{} transaction sent: {:?}", what, pending_tx.tx_hash());
+    Ok(Some(confirm(client, pending_tx, what).await?.transaction_hash))
+}
+
+// Waits for the chain's confirmation depth and fails if the transaction reverted. The
+// receipt is only trusted while its block is canonical; after a reorg it is waited for again.
+async fn confirm(
+    client: &ChainClient,
+    pending_tx: PendingTransaction<'_, <SignerClient as Middleware>::Provider>,
+    what: &str,
+) -> Result<TransactionReceipt> {
+    let provider = client.provider();
+    let mut pending_tx = pending_tx;
+    let tx_hash = pending_tx.tx_hash();
+    let receipt = loop {
+        let receipt = pending_tx
+            .confirmations(client.chain.confirmations.max(1) as usize)
+            .await?
+            // Dropped from the mempool, usually replaced by another transaction with the same nonce
+            .ok_or_else(|| Error::Nonce(format!("{} transaction {:?} was dropped", what, tx_hash)))?;
+        if canonical(&provider, &receipt).await? {
+            break receipt;
+        }
+        println_time!(
+            "{} transaction {:?} was reorged out of block {:?}, waiting for it again",
+            what,
+            tx_hash,
+            receipt.block_number
+        );
+        pending_tx = PendingTransaction::new(tx_hash, &provider);
+    };
+    if receipt.status != Some(1.into()) {
+        return Err(revert_reason(client, &receipt).await);
+    }
+    println_time!("{} transaction confirmed in block {:?}", what, receipt.block_number);
+    Ok(receipt)
+}
+
+// Whether the block of a receipt is still the one the chain has at that height
+async fn canonical(provider: &ChainProvider, receipt: &TransactionReceipt) -> Result<bool> {
+    let (Some(number), Some(hash)) = (receipt.block_number, receipt.block_hash) else {
+        return Ok(false);
+    };
+    let block = provider.get_block(number).await?;
+    Ok(block.and_then(|block| block.hash) == Some(hash))
+}
+
+// Replays a mined transaction that failed on the state before its block to recover the reason
+async fn revert_reason(client: &ChainClient, receipt: &TransactionReceipt) -> Error {
+    let provider = client.provider();
+    let unknown = || Error::Revert {
+        reason: format!("transaction {:?} failed without a known reason", receipt.transaction_hash),
+    };
+    let (Ok(Some(tx)), Some(block)) = (provider.get_transaction(receipt.transaction_hash).await, receipt.block_number) else {
+        return unknown();
+    };
+    let call: TypedTransaction = (&tx).into();
+    match provider.call(&call, Some(BlockId::from(block.saturating_sub(1.into())))).await {
+        Err(error) => match Error::from(error) {
+            error @ Error::Revert { .. } => error,
+            _ => unknown(),
+        },
+        Ok(_) => unknown(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::utils::parse_units;
+
+    fn gwei(amount: &str) -> U256 {
+        parse_units(amount, "gwei").unwrap().into()
+    }
+
+    #[test]
+    fn the_fee_cap_covers_one_full_block_of_base_fee_growth() {
+        let rewards = vec![gwei("3"), U256::zero(), gwei("1"), gwei("2")];
+        let fees = sweep_fees(gwei("80"), rewards);
+        // 80 * 9/8 + the median reward of 2
+        assert_eq!(
+            fees,
+            SweepFees::Eip1559 { max_fee_per_gas: gwei("92"), max_priority_fee_per_gas: gwei("12") }
+        );
+
+        // Empty blocks paid no reward to take the median of
+        let fees = sweep_fees(gwei("80"), vec![U256::zero(); 10]);
+        assert_eq!(fees.max_gas_price(), gwei("90") + U256::from(FALLBACK_PRIORITY_FEE));
+    }
+
+    #[test]
+    fn a_sweep_leaves_exactly_the_reserve() {
+        let fees = sweep_fees(gwei("80"), vec![gwei("2")]);
+        let gas_cost = worst_case_cost(fees, U256::from(21_000), U256::zero()).unwrap();
+        assert_eq!(gas_cost, gwei("92") * 21_000);
+
+        let (balance, reserve) = (U256::exp10(18), U256::exp10(16));
+        let amount = sweep_amount(balance, gas_cost, reserve).unwrap();
+        assert_eq!(amount + gas_cost, balance - reserve);
+    }
+
+    #[test]
+    fn balances_that_do_not_cover_the_fee_are_not_swept() {
+        let gas_cost = gwei("92") * 21_000;
+        assert_eq!(sweep_amount(gas_cost - 1, gas_cost, U256::zero()), None);
+        assert_eq!(sweep_amount(gas_cost, gas_cost, U256::zero()), None);
+        assert_eq!(sweep_amount(gas_cost + 5, gas_cost, U256::from(5)), None);
+        assert_eq!(sweep_amount(gas_cost + 6, gas_cost, U256::from(5)), Some(U256::one()));
+    }
+
+    #[test]
+    fn the_l1_data_fee_gets_a_quarter_on_top() {
+        let fees = SweepFees::Legacy { gas_price: gwei("1") };
+        let gas_cost = worst_case_cost(fees, U256::from(21_000), U256::from(800_000)).unwrap();
+        assert_eq!(gas_cost, gwei("1") * 21_000 + 1_000_000);
+
+        assert!(worst_case_cost(SweepFees::Legacy { gas_price: U256::MAX }, U256::from(2), U256::zero()).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{bail, Context};
+use ethers::types::Address;
+use std::collections::HashSet;
+use std::fs;
+
+// A single address being monitored, with a human readable label for alerts
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchEntry {
+    pub address: Address,
+    pub label: String,
+    pub alerts: AlertDirections,
+}
+
+// Which blacklist changes of an address are sent to Telegram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlertDirections {
+    pub on_add: bool,
+    pub on_remove: bool,
+    pub on_destroy: bool,
+}
+
+impl AlertDirections {
+    /// Parses a `+` or `,` separated list of `add`, `remove`, `destroy`, `all` or `none`.
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let mut directions = AlertDirections { on_add: false, on_remove: false, on_destroy: false };
+        for item in value.split(['+', ',']).map(str::trim).filter(|item| !item.is_empty()) {
+            match item {
+                "add" => directions.on_add = true,
+                "remove" => directions.on_remove = true,
+                "destroy" => directions.on_destroy = true,
+                "all" => {
+                    directions = AlertDirections { on_add: true, on_remove: true, on_destroy: true };
+                }
+                "none" => {}
+                other => bail!("Unknown alert direction {}, expected add, remove, destroy, all or none", other),
+            }
+        }
+        Ok(directions)
+    }
+}
+
+/// Reads a watchlist file with one `<address> <label> [alerts=<directions>]` entry per
+/// line (`#` starts a comment, label and alerts are optional).
+pub fn read_file(path: &str, defaults: AlertDirections) -> anyhow::Result<Vec<anyhow::Result<WatchEntry>>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read watchlist file {}", path))?;
+    Ok(parse_file(&content, defaults)
+        .into_iter()
+        .map(|entry| entry.with_context(|| format!("In watchlist file {}", path)))
+        .collect())
+}
+
+fn parse_file(content: &str, defaults: AlertDirections) -> Vec<anyhow::Result<WatchEntry>> {
+    let mut entries = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (address, rest) = match line.split_once(char::is_whitespace) {
+            Some((address, rest)) => (address, rest.trim()),
+            None => (line, ""),
+        };
+        // An optional trailing alerts=... token follows the label
+        let (label, alerts) = match rest.rsplit_once(char::is_whitespace) {
+            Some((label, last)) if last.starts_with("alerts=") => (label.trim(), last.strip_prefix("alerts=")),
+            _ => match rest.strip_prefix("alerts=") {
+                Some(alerts) => ("", Some(alerts)),
+                None => (rest, None),
+            },
+        };
+        let entry = parse_entry(address, label, alerts, defaults)
+            .with_context(|| format!("Invalid watchlist entry on line {}", index + 1));
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Parses comma separated `<address>=<label>[;alerts=<directions>]` entries.
+pub fn parse_list(list: &str, defaults: AlertDirections) -> Vec<anyhow::Result<WatchEntry>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (entry, alerts) = match item.split_once(';') {
+                Some((entry, options)) => match options.trim().strip_prefix("alerts=") {
+                    Some(alerts) => (entry, Some(alerts)),
+                    None => bail!("Invalid watchlist option {}, expected alerts=<directions>", options),
+                },
+                None => (item, None),
+            };
+            let (address, label) = entry.split_once('=').unwrap_or((entry, ""));
+            parse_entry(address.trim(), label.trim(), alerts, defaults)
+        })
+        .collect()
+}
+
+/// Parses one entry; the label defaults to the address and the alerts to `defaults`.
+pub fn parse_entry(
+    address: &str,
+    label: &str,
+    alerts: Option<&str>,
+    defaults: AlertDirections,
+) -> anyhow::Result<WatchEntry> {
+    let parsed: Address = address
+        .parse()
+        .with_context(|| format!("Invalid address {}", address))?;
+    // Fall back to the address itself when no label was given
+    let label = if label.is_empty() {
+        format!("{:?}", parsed)
+    } else {
+        label.to_string()
+    };
+    let alerts = match alerts {
+        Some(alerts) => AlertDirections::parse(alerts)?,
+        None => defaults,
+    };
+    Ok(WatchEntry { address: parsed, label, alerts })
+}
+
+// Keep the first occurrence of each address so a duplicate line does not double alerts
+pub fn dedup(entries: Vec<WatchEntry>) -> Vec<WatchEntry> {
+    let mut seen = HashSet::new();
+    entries
+        .into_iter()
+        .filter(|entry| seen.insert(entry.address))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REMOVE: AlertDirections = AlertDirections { on_add: false, on_remove: true, on_destroy: false };
+    const ALL: AlertDirections = AlertDirections { on_add: true, on_remove: true, on_destroy: true };
+    const A: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const B: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+    fn address(value: &str) -> Address {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_alert_directions() {
+        assert_eq!(AlertDirections::parse("all").unwrap(), ALL);
+        assert_eq!(AlertDirections::parse("none").unwrap(), AlertDirections { on_remove: false, ..REMOVE });
+        assert_eq!(
+            AlertDirections::parse("add+destroy").unwrap(),
+            AlertDirections { on_add: true, on_remove: false, on_destroy: true }
+        );
+        assert_eq!(AlertDirections::parse(" add , remove ").unwrap(), AlertDirections { on_add: true, ..REMOVE });
+        assert!(AlertDirections::parse("add+freeze").is_err());
+    }
+
+    #[test]
+    fn parses_file_lines_with_comments_labels_and_alerts() {
+        let content = format!(
+            "# treasury wallets\n\n{A} cold storage  # main\n{B} alerts=all\n{A}\n{B} hot wallet alerts=add\n"
+        );
+        let entries: Vec<WatchEntry> = parse_file(&content, REMOVE).into_iter().map(Result::unwrap).collect();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0], WatchEntry { address: address(A), label: "cold storage".to_string(), alerts: REMOVE });
+        assert_eq!(entries[1].label, format!("{:?}", address(B)));
+        assert_eq!(entries[1].alerts, ALL);
+        assert_eq!(entries[2].label, format!("{:?}", address(A)));
+        assert_eq!(entries[3].label, "hot wallet");
+        assert_eq!(entries[3].alerts, AlertDirections { on_add: true, on_remove: false, on_destroy: false });
+    }
+
+    #[test]
+    fn reports_the_line_of_an_invalid_entry() {
+        let entries = parse_file(&format!("{A} ok\n0x1234 short\n"), REMOVE);
+        assert!(entries[0].is_ok());
+        let error = format!("{:#}", entries[1].as_ref().unwrap_err());
+        assert!(error.contains("line 2"), "{}", error);
+    }
+
+    #[test]
+    fn parses_inline_lists() {
+        let entries = parse_list(&format!("{A}=treasury;alerts=all, {B} ,"), REMOVE);
+        let entries: Vec<WatchEntry> = entries.into_iter().map(Result::unwrap).collect();
+        assert_eq!(entries, [
+            WatchEntry { address: address(A), label: "treasury".to_string(), alerts: ALL },
+            WatchEntry { address: address(B), label: format!("{:?}", address(B)), alerts: REMOVE },
+        ]);
+        assert!(parse_list(&format!("{A}=treasury;notify=all"), REMOVE)[0].is_err());
+    }
+
+    #[test]
+    fn dedup_keeps_the_first_entry_of_an_address() {
+        let entries = parse_list(&format!("{A}=first,{B}=other,{A}=second"), REMOVE);
+        let entries = dedup(entries.into_iter().map(Result::unwrap).collect());
+        let labels: Vec<&str> = entries.iter().map(|entry| entry.label.as_str()).collect();
+        assert_eq!(labels, ["first", "other"]);
+    }
+}